@@ -0,0 +1,731 @@
+//! Live tracking through the NDI Combined API, over a serial line or TCP.
+//!
+//! Frames are polled with `TX` (ASCII) or `BX` (binary) requesting transformation data with
+//! tool and marker information, and parsed into the same [`Frame`] as the CSV reader.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::{Error, Result};
+use crate::frame::{Frame, State, ToolRecord};
+use crate::polaris::Port;
+use crate::tool::ToolLibrary;
+
+/// Transformation data (0x0001) with tool and marker information (0x0008).
+pub const REPLY_OPTIONS: u16 = 0x0009;
+// Start of every BX reply, 0xA5C4 little endian
+const BX_START: [u8; 2] = [0xC4, 0xA5];
+// Port status bits
+const ENABLED: u32 = 0x31;
+const OUT_OF_VOLUME: u32 = 0x40;
+const PARTIALLY_OUT_OF_VOLUME: u32 = 0x80;
+// Handle status in BX replies
+const VALID: u8 = 0x01;
+const MISSING: u8 = 0x02;
+const DISABLED: u8 = 0x04;
+// Marker information holds one status per marker; 3 means used for the transformation
+const MARKERS: usize = 20;
+const MARKER_USED: u8 = 3;
+// Any hardware device and port, wireless (passive) tool
+const PASSIVE_TOOL: &str = "*********1****";
+// SROM bytes written by one PVWR
+const SROM_CHUNK: usize = 64;
+/// Longest wait for a reply over TCP before giving up on the tracker.
+pub const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// Corrupted or malformed replies in a row skipped by [`Connection::frames`] before it fails.
+pub const MAX_BAD_REPLIES: usize = 3;
+
+/// CRC-16 of commands and replies: polynomial 0x8005, reflected, zero initial value.
+pub fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0, |crc, &b| {
+        (0..8).fold(crc ^ b as u16, |crc, _| if crc & 1 != 0 { (crc >> 1) ^ 0xA001 } else { crc >> 1 })
+    })
+}
+
+pub(crate) fn check_crc(data: &[u8], found: u16) -> Result<()> {
+    let expected = crc16(data);
+    if expected == found {
+        Ok(())
+    } else {
+        Err(Error::Crc { expected, found })
+    }
+}
+
+/// Command used to poll frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReplyFormat {
+    /// `TX`: quaternions to four decimals and positions to 0.01 mm.
+    Ascii,
+    /// `BX`: single precision throughout.
+    #[default]
+    Binary,
+}
+
+fn now() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs_f64()).unwrap_or_default()
+}
+
+fn state(status: u32) -> State {
+    if status & OUT_OF_VOLUME != 0 {
+        State::OutOfVolume
+    } else if status & PARTIALLY_OUT_OF_VOLUME != 0 {
+        State::PartiallyOutOfVolume
+    } else {
+        State::Ok
+    }
+}
+
+fn port_status(state: State) -> u32 {
+    match state {
+        State::OutOfVolume => ENABLED | OUT_OF_VOLUME,
+        State::PartiallyOutOfVolume => ENABLED | PARTIALLY_OUT_OF_VOLUME,
+        _ => ENABLED,
+    }
+}
+
+// Handles that were never described get a placeholder port
+fn port(ports: &HashMap<u8, Port>, handle: u8) -> Port {
+    ports.get(&handle).cloned().unwrap_or_else(|| Port {
+        handle,
+        name: format!("Handle {handle:02X}"),
+        serial: String::new(),
+    })
+}
+
+/// Pose as `[q0, qx, qy, qz]`, `[tx, ty, tz]` and the RMS error.
+type Pose = ([f32; 4], [f32; 3], f32);
+
+fn record(port: Port, frame: u32, time: f64, state: State, pose: Option<Pose>, markers: u32) -> ToolRecord {
+    ToolRecord {
+        port,
+        frame: frame as u64,
+        time,
+        state,
+        rotation: pose.map(|p| p.0),
+        translation: pose.map(|p| p.1),
+        error: pose.map(|p| p.2),
+        markers,
+    }
+}
+
+// Fixed-width fields of an ASCII reply
+struct Fields<'a> {
+    text: &'a str,
+    at: usize,
+}
+
+impl<'a> Fields<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { text, at: 0 }
+    }
+    fn rest(&self) -> &'a str {
+        &self.text[self.at..]
+    }
+    fn take(&mut self, n: usize) -> Result<&'a str> {
+        let field = self
+            .text
+            .get(self.at..self.at + n)
+            .ok_or_else(|| Error::Reply(format!("expected {n} characters at offset {}", self.at)))?;
+        self.at += n;
+        Ok(field)
+    }
+    fn hex(&mut self, n: usize) -> Result<u32> {
+        let field = self.take(n)?;
+        u32::from_str_radix(field, 16).map_err(|_| Error::Reply(format!("`{field}` is not hexadecimal")))
+    }
+    // Sign and digits with an implied decimal point
+    fn fixed(&mut self, n: usize, decimals: i32) -> Result<f32> {
+        let field = self.take(n)?;
+        let value: i32 = field.parse().map_err(|_| Error::Reply(format!("`{field}` is not a number")))?;
+        Ok(value as f32 / 10f32.powi(decimals))
+    }
+    fn line_feed(&mut self) -> Result<()> {
+        match self.take(1)? {
+            "\n" => Ok(()),
+            other => Err(Error::Reply(format!("expected a line feed, found `{other}`"))),
+        }
+    }
+}
+
+/// Parses the body of a `TX` reply, without its CRC. `time` stamps every tool.
+pub fn parse_tx(body: &str, ports: &HashMap<u8, Port>, time: f64) -> Result<Frame> {
+    let mut f = Fields::new(body);
+    let count = f.hex(2)?;
+    let tools = (0..count)
+        .map(|_| {
+            let port = port(ports, f.hex(2)? as u8);
+            let tool = if f.rest().starts_with("DISABLED") {
+                f.take(8)?;
+                record(port, 0, time, State::Disabled, None, 0)
+            } else {
+                let missing = f.rest().starts_with("MISSING");
+                let pose = if missing {
+                    f.take(7)?;
+                    None
+                } else {
+                    let rotation = [f.fixed(6, 4)?, f.fixed(6, 4)?, f.fixed(6, 4)?, f.fixed(6, 4)?];
+                    let translation = [f.fixed(7, 2)?, f.fixed(7, 2)?, f.fixed(7, 2)?];
+                    Some((rotation, translation, f.fixed(6, 4)?))
+                };
+                let status = f.hex(8)?;
+                let frame = f.hex(8)?;
+                f.hex(2)?; // tool information
+                let markers = f.take(MARKERS)?.bytes().filter(|&c| c == b'0' + MARKER_USED).count() as u32;
+                let state = if missing { State::Missing } else { state(status) };
+                record(port, frame, time, state, pose, markers)
+            };
+            f.line_feed()?;
+            Ok(tool)
+        })
+        .collect::<Result<_>>()?;
+    f.hex(4)?; // system status
+    Ok(Frame { tools })
+}
+
+fn marker_count(tool: &ToolRecord) -> usize {
+    (tool.markers as usize).min(MARKERS)
+}
+
+/// `TX` reply body for `frame`, without its CRC.
+pub fn format_tx(frame: &Frame) -> String {
+    let mut s = format!("{:02X}", frame.tools.len());
+    for tool in &frame.tools {
+        write!(s, "{:02X}", tool.port.handle).unwrap();
+        if tool.state == State::Disabled {
+            s.push_str("DISABLED\n");
+            continue;
+        }
+        match (tool.rotation, tool.translation) {
+            (Some(rotation), Some(translation)) if tool.state != State::Missing => {
+                for q in rotation {
+                    write!(s, "{:+06}", (q * 1e4).round() as i32).unwrap();
+                }
+                for t in translation {
+                    write!(s, "{:+07}", (t * 1e2).round() as i32).unwrap();
+                }
+                write!(s, "{:+06}", (tool.error.unwrap_or(0.0) * 1e4).round() as i32).unwrap();
+            }
+            _ => s.push_str("MISSING"),
+        }
+        let markers = marker_count(tool);
+        write!(s, "{:08X}{:08X}00", port_status(tool.state), tool.frame as u32).unwrap();
+        s.extend((0..MARKERS).map(|i| if i < markers { '3' } else { '0' }));
+        s.push('\n');
+    }
+    s.push_str("0000");
+    s
+}
+
+// Little-endian fields of a BX reply
+struct Bytes<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Bytes<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let field = self
+            .data
+            .get(self.at..self.at + n)
+            .ok_or_else(|| Error::Reply(format!("binary reply truncated at byte {}", self.at)))?;
+        self.at += n;
+        Ok(field)
+    }
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+    fn f32(&mut self) -> Result<f32> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+/// Parses a complete `BX` reply, checking both CRCs. `time` stamps every tool.
+pub fn parse_bx(reply: &[u8], ports: &HashMap<u8, Port>, time: f64) -> Result<Frame> {
+    let mut header = Bytes { data: reply, at: 0 };
+    if header.take(2)? != BX_START {
+        return Err(Error::Reply("missing BX start sequence".to_string()));
+    }
+    let length = header.u16()? as usize;
+    let header_crc = header.u16()?;
+    check_crc(&reply[..4], header_crc)?;
+    let body = header.take(length)?;
+    check_crc(body, header.u16()?)?;
+
+    let mut b = Bytes { data: body, at: 0 };
+    let count = b.u8()?;
+    let tools = (0..count)
+        .map(|_| {
+            let port = port(ports, b.u8()?);
+            let status = b.u8()?;
+            if status == DISABLED {
+                return Ok(record(port, 0, time, State::Disabled, None, 0));
+            }
+            let pose = match status {
+                VALID => {
+                    let rotation = [b.f32()?, b.f32()?, b.f32()?, b.f32()?];
+                    let translation = [b.f32()?, b.f32()?, b.f32()?];
+                    Some((rotation, translation, b.f32()?))
+                }
+                MISSING => None,
+                other => return Err(Error::Reply(format!("unknown handle status {other:02X}"))),
+            };
+            let port_status = b.u32()?;
+            let frame = b.u32()?;
+            b.u8()?; // tool information
+            // Two markers per byte, low nibble first
+            let markers = b
+                .take(MARKERS / 2)?
+                .iter()
+                .flat_map(|m| [m & 0x0F, m >> 4])
+                .filter(|&m| m == MARKER_USED)
+                .count() as u32;
+            let state = if pose.is_some() { state(port_status) } else { State::Missing };
+            Ok(record(port, frame, time, state, pose, markers))
+        })
+        .collect::<Result<_>>()?;
+    b.u16()?; // system status
+    Ok(Frame { tools })
+}
+
+/// Complete `BX` reply for `frame`, with header and CRCs.
+pub fn format_bx(frame: &Frame) -> Vec<u8> {
+    let mut body = vec![frame.tools.len() as u8];
+    for tool in &frame.tools {
+        body.push(tool.port.handle);
+        if tool.state == State::Disabled {
+            body.push(DISABLED);
+            continue;
+        }
+        match (tool.rotation, tool.translation) {
+            (Some(rotation), Some(translation)) if tool.state != State::Missing => {
+                body.push(VALID);
+                for v in rotation.into_iter().chain(translation).chain([tool.error.unwrap_or(0.0)]) {
+                    body.extend(v.to_le_bytes());
+                }
+            }
+            _ => body.push(MISSING),
+        }
+        body.extend(port_status(tool.state).to_le_bytes());
+        body.extend((tool.frame as u32).to_le_bytes());
+        body.push(0);
+        let markers = marker_count(tool);
+        body.extend((0..MARKERS / 2).map(|i| {
+            let nibble = |m: usize| if m < markers { MARKER_USED } else { 0 };
+            nibble(2 * i) | nibble(2 * i + 1) << 4
+        }));
+    }
+    body.extend(0u16.to_le_bytes());
+
+    let mut reply = BX_START.to_vec();
+    reply.extend((body.len() as u16).to_le_bytes());
+    reply.extend(crc16(&reply).to_le_bytes());
+    reply.extend(crc16(&body).to_le_bytes());
+    reply.splice(6..6, body);
+    reply
+}
+
+/// Tool description from a `PHINF` reply with option 0x0001.
+pub fn parse_phinf(handle: u8, reply: &str) -> Result<Port> {
+    let mut f = Fields::new(reply);
+    f.take(8)?; // tool type
+    let manufacturer = f.take(12)?.trim();
+    f.take(3)?; // revision
+    let serial = f.take(8)?.trim();
+    Ok(Port {
+        handle,
+        name: manufacturer.to_string(),
+        serial: serial.to_string(),
+    })
+}
+
+/// Session with a tracker over any byte stream.
+#[derive(Debug)]
+pub struct Connection<S: Read + Write> {
+    stream: BufReader<S>,
+    ports: HashMap<u8, Port>,
+    tools: ToolLibrary,
+    format: ReplyFormat,
+}
+
+impl Connection<TcpStream> {
+    /// Connects with a [`READ_TIMEOUT`], so a tracker that stops answering is an error rather
+    /// than a hang.
+    pub fn connect<A: ToSocketAddrs>(address: A) -> Result<Self> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        Ok(Self::new(stream))
+    }
+}
+
+impl Connection<File> {
+    /// Opens a serial device or pseudo-terminal. Line settings are not changed, so a serial
+    /// port must already be configured, e.g. with `stty`.
+    pub fn open_serial<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::new(OpenOptions::new().read(true).write(true).open(path)?))
+    }
+}
+
+impl<S: Read + Write> Connection<S> {
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream: BufReader::new(stream),
+            ports: HashMap::new(),
+            tools: ToolLibrary::new(),
+            format: ReplyFormat::default(),
+        }
+    }
+    pub fn with_format(mut self, format: ReplyFormat) -> Self {
+        self.format = format;
+        self
+    }
+    /// Names tools by handle instead of asking the tracker, e.g. with [`polaris::ports`] of an
+    /// export from the same setup; `PHINF` only reports the manufacturer and serial number.
+    ///
+    /// [`polaris::ports`]: crate::polaris::ports
+    pub fn with_ports<I: IntoIterator<Item = Port>>(mut self, ports: I) -> Self {
+        self.ports.extend(ports.into_iter().map(|p| (p.handle, p)));
+        self
+    }
+    pub fn ports(&self) -> &HashMap<u8, Port> {
+        &self.ports
+    }
+    /// Passive tools to describe to the tracker on [`start`](Self::start): every definition
+    /// read from a `.rom` file is uploaded to a new port handle, which is named after it.
+    pub fn with_tools(mut self, tools: ToolLibrary) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    fn send(&mut self, command: &str, parameters: &str) -> Result<()> {
+        let text = format!("{command}:{parameters}");
+        let stream = self.stream.get_mut();
+        write!(stream, "{text}{:04X}\r", crc16(text.as_bytes()))?;
+        stream.flush()?;
+        Ok(())
+    }
+
+    // ASCII reply without its CRC; `start` holds bytes already read
+    fn read_reply(&mut self, start: Vec<u8>) -> Result<String> {
+        let mut line = start;
+        if self.stream.read_until(b'\r', &mut line)? == 0 && line.is_empty() {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
+        if line.pop() != Some(b'\r') {
+            return Err(Error::Reply("connection closed during a reply".to_string()));
+        }
+        let text = String::from_utf8(line)
+            .ok()
+            .filter(|t| t.is_ascii() && t.len() >= 4)
+            .ok_or_else(|| Error::Reply("expected an ASCII reply".to_string()))?;
+        let (body, crc) = text.split_at(text.len() - 4);
+        let found = u16::from_str_radix(crc, 16).map_err(|_| Error::Reply(format!("`{crc}` is not a CRC")))?;
+        check_crc(body.as_bytes(), found)?;
+        if body.starts_with("ERROR") {
+            return Err(Error::Device(body.to_string()));
+        }
+        Ok(body.to_string())
+    }
+
+    /// Sends a command and returns its reply without the CRC.
+    pub fn command(&mut self, command: &str, parameters: &str) -> Result<String> {
+        self.send(command, parameters)?;
+        self.read_reply(Vec::new())
+    }
+
+    // Handles listed by `PHSR` for one kind of query
+    fn handles(&mut self, kind: &str) -> Result<Vec<u8>> {
+        let reply = self.command("PHSR", kind)?;
+        let mut f = Fields::new(&reply);
+        let count = f.hex(2)?;
+        (0..count)
+            .map(|_| {
+                let handle = f.hex(2)? as u8;
+                f.take(3)?; // handle status
+                Ok(handle)
+            })
+            .collect()
+    }
+
+    // Requests a port handle for a passive tool and writes its SROM image to it
+    fn upload(&mut self, rom: &[u8]) -> Result<u8> {
+        let reply = self.command("PHRQ", PASSIVE_TOOL)?;
+        let handle = Fields::new(&reply).hex(2)? as u8;
+        for (i, chunk) in rom.chunks(SROM_CHUNK).enumerate() {
+            let mut data = String::with_capacity(2 * SROM_CHUNK);
+            for byte in chunk.iter().chain(std::iter::repeat(&0)).take(SROM_CHUNK) {
+                write!(data, "{byte:02X}").unwrap();
+            }
+            self.command("PVWR", &format!("{handle:02X}{:04X}{data}", i * SROM_CHUNK))?;
+        }
+        Ok(handle)
+    }
+
+    /// Initialises the tracker, uploads the definitions given to
+    /// [`with_tools`](Self::with_tools), initialises and enables every tool, describes any tool
+    /// not given to [`with_ports`](Self::with_ports) and starts tracking.
+    pub fn start(&mut self) -> Result<()> {
+        self.command("INIT", "")?;
+        let uploads: Vec<_> = self
+            .tools
+            .iter()
+            .filter_map(|t| Some((t.rom.clone()?, t.name.clone(), t.serial.clone())))
+            .collect();
+        for (rom, name, serial) in uploads {
+            let handle = self.upload(&rom)?;
+            self.ports.entry(handle).or_insert(Port { handle, name, serial });
+        }
+        for handle in self.handles("02")? {
+            self.command("PINIT", &format!("{handle:02X}"))?;
+        }
+        for handle in self.handles("03")? {
+            // Dynamic tool
+            self.command("PENA", &format!("{handle:02X}D"))?;
+        }
+        for handle in self.handles("04")? {
+            if !self.ports.contains_key(&handle) {
+                let info = self.command("PHINF", &format!("{handle:02X}0001"))?;
+                self.ports.insert(handle, parse_phinf(handle, &info)?);
+            }
+        }
+        self.command("TSTART", "")?;
+        Ok(())
+    }
+    pub fn stop(&mut self) -> Result<()> {
+        self.command("TSTOP", "")?;
+        Ok(())
+    }
+
+    /// Polls the latest frame, stamped with the host clock in seconds since the Unix epoch.
+    pub fn frame(&mut self) -> Result<Frame> {
+        let options = format!("{REPLY_OPTIONS:04X}");
+        match self.format {
+            ReplyFormat::Ascii => {
+                self.send("TX", &options)?;
+                let body = self.read_reply(Vec::new())?;
+                parse_tx(&body, &self.ports, now())
+            }
+            ReplyFormat::Binary => {
+                self.send("BX", &options)?;
+                let mut reply = vec![0; 6];
+                self.stream.read_exact(&mut reply[..2])?;
+                if reply[..2] != BX_START {
+                    // Errors are always ASCII
+                    self.read_reply(reply[..2].to_vec())?;
+                    return Err(Error::Reply("expected a binary reply".to_string()));
+                }
+                self.stream.read_exact(&mut reply[2..])?;
+                let length = u16::from_le_bytes([reply[2], reply[3]]) as usize;
+                reply.resize(6 + length + 2, 0);
+                self.stream.read_exact(&mut reply[6..])?;
+                parse_bx(&reply, &self.ports, now())
+            }
+        }
+    }
+
+    /// Frames polled back to back. A corrupted or malformed reply is skipped and the frame
+    /// polled again, up to [`MAX_BAD_REPLIES`] in a row; any other error, or one reply too
+    /// many, ends the frames and is yielded.
+    pub fn frames(&mut self) -> impl Iterator<Item = Result<Frame>> + '_ {
+        let mut failed = false;
+        std::iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+            let mut bad = 0;
+            let frame = loop {
+                match self.frame() {
+                    Err(Error::Crc { .. } | Error::Reply(_)) if bad < MAX_BAD_REPLIES => bad += 1,
+                    frame => break frame,
+                }
+            };
+            failed = frame.is_err();
+            Some(frame)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::TcpListener;
+
+    fn ports() -> HashMap<u8, Port> {
+        crate::polaris::ports("data.csv").unwrap().into_iter().map(|p| (p.handle, p)).collect()
+    }
+
+    // Frame as the tracker would report it: no timestamps and pose precision of the reply
+    fn frame() -> Frame {
+        let mut frame = crate::polaris::read("data.csv").unwrap().remove(0);
+        frame.tools[1].state = State::Missing;
+        frame.tools[1].rotation = None;
+        frame.tools[1].translation = None;
+        frame.tools[1].error = None;
+        frame.tools[1].markers = 0;
+        frame.tools[2].state = State::PartiallyOutOfVolume;
+        frame
+    }
+
+    #[test]
+    fn crc_matches_reference_value() {
+        assert_eq!(crc16(b"123456789"), 0xBB3D);
+    }
+
+    #[test]
+    fn binary_replies_round_trip() {
+        let mut expected = frame();
+        let mut reply = format_bx(&expected);
+        let parsed = parse_bx(&reply, &ports(), 1.5).unwrap();
+        for tool in &mut expected.tools {
+            tool.time = 1.5;
+        }
+        assert_eq!(parsed, expected);
+
+        reply[10] ^= 1;
+        assert!(matches!(parse_bx(&reply, &ports(), 0.0), Err(Error::Crc { .. })));
+    }
+
+    #[test]
+    fn ascii_replies_keep_reply_precision() {
+        let expected = frame();
+        let parsed = parse_tx(&format_tx(&expected), &ports(), 0.0).unwrap();
+        assert_eq!(parsed.tools.len(), 3);
+        for (a, b) in parsed.tools.iter().zip(&expected.tools) {
+            assert_eq!((a.state, a.frame, &a.port), (b.state, b.frame, &b.port));
+            assert_eq!(a.rotation.is_some(), b.rotation.is_some());
+            for (x, y) in a.rotation.unwrap_or_default().iter().zip(b.rotation.unwrap_or_default()) {
+                assert!((x - y).abs() <= 5e-5);
+            }
+            for (x, y) in a.translation.unwrap_or_default().iter().zip(b.translation.unwrap_or_default()) {
+                assert!((x - y).abs() <= 5e-3);
+            }
+        }
+        assert_eq!(parsed.tools[0].markers, 3);
+        assert!(parse_tx("01", &ports(), 0.0).is_err());
+    }
+
+    // Answers commands from one client the way a tracker with the tools of `frame` would,
+    // corrupting the BX replies picked by `corrupt`, and returns the commands received
+    fn fake_tracker(listener: TcpListener, frame: Frame, corrupt: fn(usize) -> bool) -> Vec<String> {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut stream = stream;
+        let mut line = Vec::new();
+        let mut commands = Vec::new();
+        let mut polls = 0;
+        while reader.read_until(b'\r', &mut line).unwrap() > 0 {
+            let text = String::from_utf8(std::mem::take(&mut line)).unwrap();
+            let (command, _) = text.split_once(':').unwrap();
+            commands.push(text.trim_end().to_string());
+            let ascii = |body: String| format!("{body}{:04X}\r", crc16(body.as_bytes())).into_bytes();
+            let reply = match command {
+                "BX" => {
+                    let mut reply = format_bx(&frame);
+                    polls += 1;
+                    if corrupt(polls) {
+                        reply[10] ^= 1;
+                    }
+                    reply
+                }
+                "PHRQ" => ascii("01".to_string()),
+                "TX" => ascii(format_tx(&frame)),
+                "PHSR" if text.starts_with("PHSR:04") => {
+                    let handles: String = frame.tools.iter().map(|t| format!("{:02X}001", t.port.handle)).collect();
+                    ascii(format!("{:02X}{handles}", frame.tools.len()))
+                }
+                "PHSR" => ascii("00".to_string()),
+                "PHINF" => ascii(format!("{:<8}{:<12}{:<3}{:<8}01", "01010000", "BrainLAB", "000", "3B21FC02")),
+                "TSTOP" => ascii("ERROR0C".to_string()),
+                _ => ascii("OKAY".to_string()),
+            };
+            stream.write_all(&reply).unwrap();
+        }
+        commands
+    }
+
+    #[test]
+    fn polls_frames_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let expected = frame();
+        let served = expected.clone();
+        let server = std::thread::spawn(move || fake_tracker(listener, served, |_| false));
+
+        let known = ports().remove(&1).unwrap();
+        let mut connection = Connection::connect(address).unwrap().with_ports([known.clone()]);
+        connection.start().unwrap();
+        assert_eq!(connection.ports()[&1], known);
+        assert_eq!(connection.ports()[&2].serial, "3B21FC02");
+
+        let frames: Vec<_> = connection.frames().take(3).collect::<Result<_>>().unwrap();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].tools[0].translation, expected.tools[0].translation);
+        assert!(frames[0].tool("3B21FC02").is_some_and(|t| t.state == State::Missing));
+
+        let mut connection = connection.with_format(ReplyFormat::Ascii);
+        let frame = connection.frame().unwrap();
+        assert_eq!(frame.tools[2].state, State::PartiallyOutOfVolume);
+        assert!(matches!(connection.stop(), Err(Error::Device(code)) if code == "ERROR0C"));
+        drop(connection);
+        let commands = server.join().unwrap();
+        // Nothing to upload without tool definitions
+        assert!(!commands.iter().any(|c| c.starts_with("PHRQ")));
+    }
+
+    #[test]
+    fn uploads_passive_tools() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || fake_tracker(listener, frame(), |_| false));
+
+        let mut rom = vec![0u8; 100];
+        rom[..3].copy_from_slice(b"NDI");
+        rom[99] = 0xAB;
+        let tool = crate::tool::ToolDefinition::from_rom(&rom, "Y Junction", "38220010").unwrap();
+        let mut connection = Connection::connect(address).unwrap().with_tools([tool].into_iter().collect());
+        connection.start().unwrap();
+        assert_eq!(connection.ports()[&1].name, "Y Junction");
+        assert_eq!(connection.ports()[&1].serial, "38220010");
+        drop(connection);
+
+        let commands = server.join().unwrap();
+        let position = |prefix: &str| commands.iter().position(|c| c.starts_with(prefix)).unwrap();
+        assert!(position("INIT") < position("PHRQ") && position("PHRQ") < position("TSTART"));
+        let writes: Vec<_> = commands.iter().filter(|c| c.starts_with("PVWR")).collect();
+        assert_eq!(writes.len(), 2);
+        assert!(writes[0].starts_with("PVWR:0100004E4449"));
+        // The second chunk holds the last 36 bytes, padded with zeros
+        let data = &writes[1]["PVWR:010040".len()..writes[1].len() - 4];
+        assert_eq!(data.len(), 128);
+        assert_eq!(&data[70..72], "AB");
+        assert!(data[72..].chars().all(|c| c == '0'));
+    }
+
+    #[test]
+    fn skips_single_bad_replies() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        // Every other reply is corrupted, then all of them from the tenth poll on
+        let server = std::thread::spawn(move || fake_tracker(listener, frame(), |poll| poll % 2 == 0 || poll >= 10));
+
+        let mut connection = Connection::connect(address).unwrap().with_ports(ports().into_values());
+        connection.start().unwrap();
+        let frames: Vec<_> = connection.frames().collect();
+        assert_eq!(frames.len(), 6);
+        assert!(frames[..5].iter().all(Result::is_ok));
+        assert!(matches!(frames[5], Err(Error::Crc { .. })));
+        drop(connection);
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,68 @@
+use std::fmt;
+use std::io;
+
+/// Everything that can go wrong reading tracker exports and tool definitions.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Csv(csv::Error),
+    /// A cell that does not hold the expected value. Rows and columns count from 1, with the
+    /// header on row 1.
+    Parse { row: usize, column: usize, value: String },
+    /// A record shorter than the header.
+    MissingField { row: usize, column: usize },
+    /// A tool block without one of its columns; `port` is the column of the tool's port header.
+    MissingColumn { name: String, port: usize },
+    /// A header without any `Port` column, i.e. not a tracker export.
+    NoTools,
+    InvalidPort(String),
+    InvalidTool(String),
+    /// A tracker reply whose checksum does not match its contents.
+    Crc { expected: u16, found: u16 },
+    /// A tracker reply that does not follow the Combined API layout.
+    Reply(String),
+    /// An `ERRORxx` reply from the tracker, with its code.
+    Device(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{e}"),
+            Error::Csv(e) => write!(f, "{e}"),
+            Error::Parse { row, column, value } => write!(f, "row {row}, column {column}: cannot parse `{value}`"),
+            Error::MissingField { row, column } => write!(f, "row {row}: missing column {column}"),
+            Error::MissingColumn { name, port } => write!(f, "missing column `{name}` for tool in column {port}"),
+            Error::NoTools => f.write_str("no tool ports in header"),
+            Error::InvalidPort(header) => write!(f, "invalid port header: {header}"),
+            Error::InvalidTool(reason) => write!(f, "invalid tool definition: {reason}"),
+            Error::Crc { expected, found } => write!(f, "reply CRC {found:04X} does not match {expected:04X}"),
+            Error::Reply(reason) => write!(f, "malformed reply: {reason}"),
+            Error::Device(code) => write!(f, "tracker replied {code}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Csv(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(e: csv::Error) -> Self {
+        Error::Csv(e)
+    }
+}
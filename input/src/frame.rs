@@ -0,0 +1,86 @@
+use std::convert::Infallible;
+use std::str::FromStr;
+
+use crate::polaris::Port;
+
+/// Tool status as reported by the tracker for a single frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    Ok,
+    Missing,
+    Disabled,
+    PartiallyOutOfVolume,
+    OutOfVolume,
+    Unknown,
+}
+
+impl FromStr for State {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.trim().to_ascii_lowercase().as_str() {
+            "ok" => State::Ok,
+            "missing" => State::Missing,
+            "disabled" => State::Disabled,
+            "poov" | "partially out of volume" => State::PartiallyOutOfVolume,
+            "oov" | "out of volume" => State::OutOfVolume,
+            _ => State::Unknown,
+        })
+    }
+}
+
+impl std::fmt::Display for State {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            State::Ok => "OK",
+            State::Missing => "Missing",
+            State::Disabled => "Disabled",
+            State::PartiallyOutOfVolume => "POOV",
+            State::OutOfVolume => "OOV",
+            State::Unknown => "Unknown",
+        };
+        f.write_str(name)
+    }
+}
+
+/// One tool's pose and quality information in a frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolRecord {
+    pub port: Port,
+    pub frame: u64,
+    pub time: f64,
+    pub state: State,
+    /// Rotation quaternion as `[q0, qx, qy, qz]`.
+    pub rotation: Option<[f32; 4]>,
+    pub translation: Option<[f32; 3]>,
+    /// RMS marker fit error in mm.
+    pub error: Option<f32>,
+    pub markers: u32,
+}
+
+impl ToolRecord {
+    pub fn is_ok(&self) -> bool {
+        self.state == State::Ok && self.rotation.is_some() && self.translation.is_some()
+    }
+    /// Whether `label` refers to this tool, either as its full name, its serial number or a
+    /// word in its name (`"Y"` matches `"BrainLAB Y Junction"`).
+    pub fn matches(&self, label: &str) -> bool {
+        self.port.name == label
+            || self.port.serial == label
+            || self.port.name.split_whitespace().any(|w| w == label)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Frame {
+    pub tools: Vec<ToolRecord>,
+}
+
+impl Frame {
+    pub fn tool(&self, label: &str) -> Option<&ToolRecord> {
+        self.tools.iter().find(|t| t.matches(label))
+    }
+    pub fn time(&self) -> Option<f64> {
+        self.tools.first().map(|t| t.time)
+    }
+}
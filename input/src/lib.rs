@@ -1,6 +1,14 @@
 mod certus;
-mod polaris;
+pub mod combined;
+mod error;
+mod frame;
+pub mod polaris;
 mod parse_csv;
+pub mod simulator;
+pub mod tool;
+
+pub use error::{Error, Result};
+pub use frame::{Frame, State, ToolRecord};
 pub fn add(left: u64, right: u64) -> u64 {
     left + right
 }
@@ -15,4 +23,3 @@ mod tests {
         assert_eq!(result, 4);
     }
 }
-
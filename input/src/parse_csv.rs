@@ -0,0 +1,150 @@
+use std::str::FromStr;
+
+use csv::StringRecord;
+
+use crate::error::{Error, Result};
+use crate::frame::{Frame, State, ToolRecord};
+use crate::polaris::Port;
+
+/// Column indices of one tool block in an NDI Track export.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ToolColumns {
+    port: usize,
+    frame: usize,
+    time: usize,
+    state: usize,
+    rotation: [usize; 4],
+    translation: [usize; 3],
+    error: usize,
+    markers: usize,
+}
+
+pub(crate) fn layout(headers: &StringRecord) -> Result<Vec<ToolColumns>> {
+    let starts: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .filter(|(_, h)| h.starts_with("Port"))
+        .map(|(i, _)| i)
+        .collect();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(n, &port)| {
+            let end = starts.get(n + 1).copied().unwrap_or(headers.len());
+            // Marker blocks repeat State/Tx/Ty/Tz, so only the first match is the tool's own
+            let find = |name: &str| {
+                (port + 1..end)
+                    .find(|&i| &headers[i] == name)
+                    .ok_or_else(|| Error::MissingColumn {
+                        name: name.to_string(),
+                        port: port + 1,
+                    })
+            };
+            Ok(ToolColumns {
+                port,
+                frame: find("Frame")?,
+                time: find("Time [sec]")?,
+                state: find("State")?,
+                rotation: [find("Q0")?, find("Qx")?, find("Qy")?, find("Qz")?],
+                translation: [find("Tx")?, find("Ty")?, find("Tz")?],
+                error: find("Error")?,
+                markers: find("Markers")?,
+            })
+        })
+        .collect()
+}
+
+fn field(record: &StringRecord, row: usize, column: usize) -> Result<&str> {
+    record
+        .get(column)
+        .ok_or(Error::MissingField { row, column: column + 1 })
+}
+
+fn parse<T: FromStr>(record: &StringRecord, row: usize, column: usize) -> Result<T> {
+    let value = field(record, row, column)?;
+    value
+        .trim()
+        .parse()
+        .map_err(|_| Error::Parse {
+            row,
+            column: column + 1,
+            value: value.to_string(),
+        })
+}
+
+// Missing tools leave their pose columns empty
+fn parse_optional<T: FromStr>(record: &StringRecord, row: usize, column: usize) -> Result<Option<T>> {
+    match record.get(column).map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(_) => parse(record, row, column).map(Some),
+    }
+}
+
+fn parse_tool(columns: &ToolColumns, record: &StringRecord, row: usize) -> Result<ToolRecord> {
+    let port: Port = field(record, row, columns.port)?.parse()?;
+    let state = parse(record, row, columns.state)?;
+    let mut rotation = [0.0; 4];
+    let mut translation = [0.0; 3];
+    let mut complete = state != State::Missing;
+    for (value, &column) in rotation.iter_mut().zip(&columns.rotation) {
+        match parse_optional(record, row, column)? {
+            Some(v) => *value = v,
+            None => complete = false,
+        }
+    }
+    for (value, &column) in translation.iter_mut().zip(&columns.translation) {
+        match parse_optional(record, row, column)? {
+            Some(v) => *value = v,
+            None => complete = false,
+        }
+    }
+    Ok(ToolRecord {
+        port,
+        frame: parse(record, row, columns.frame)?,
+        time: parse(record, row, columns.time)?,
+        state,
+        rotation: complete.then_some(rotation),
+        translation: complete.then_some(translation),
+        error: parse_optional(record, row, columns.error)?,
+        markers: parse_optional(record, row, columns.markers)?.unwrap_or(0),
+    })
+}
+
+pub(crate) fn parse_frame(layout: &[ToolColumns], record: &StringRecord, row: usize) -> Result<Frame> {
+    let tools = layout
+        .iter()
+        .map(|columns| parse_tool(columns, record, row))
+        .collect::<Result<_>>()?;
+    Ok(Frame { tools })
+}
+
+const TOOL_HEADERS: [&str; 13] = ["Frame", "Time [sec]", "Face", "State", "Q0", "Qx", "Qy", "Qz", "Tx", "Ty", "Tz", "Error", "Markers"];
+
+pub(crate) fn headers(frame: &Frame) -> Vec<String> {
+    let mut headers = vec!["Tools".to_string()];
+    for tool in &frame.tools {
+        headers.push(tool.port.to_string());
+        headers.extend(TOOL_HEADERS.map(String::from));
+    }
+    headers
+}
+
+fn format_optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+pub(crate) fn format_frame(frame: &Frame) -> Vec<String> {
+    let mut record = vec![frame.tools.len().to_string()];
+    for tool in &frame.tools {
+        record.extend([tool.port.to_string(), tool.frame.to_string(), tool.time.to_string(), "1".to_string()]);
+        record.push(tool.state.to_string());
+        for i in 0..4 {
+            record.push(format_optional(tool.rotation.map(|r| r[i])));
+        }
+        for i in 0..3 {
+            record.push(format_optional(tool.translation.map(|t| t[i])));
+        }
+        record.extend([format_optional(tool.error), tool.markers.to_string()]);
+    }
+    record
+}
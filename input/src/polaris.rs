@@ -1,27 +1,191 @@
 use std::fs::File;
-use std::io::{self, BufRead};
+use std::str::FromStr;
 
-pub fn read(path: &str) -> io::Result<()> {
-    // let file = File::open(path)?;
+use crate::error::{Error, Result};
+use crate::frame::Frame;
+use crate::parse_csv;
+
+/// Tool port as described in the export header, e.g.
+/// `Port 0x01: BrainLAB Y Junction  s/n:38220010`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Port {
+    pub handle: u8,
+    pub name: String,
+    pub serial: String,
+}
+
+impl FromStr for Port {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidPort(s.to_string());
+        let rest = s.trim().strip_prefix("Port 0x").ok_or_else(invalid)?;
+        let (handle, rest) = rest.split_once(':').ok_or_else(invalid)?;
+        let handle = u8::from_str_radix(handle, 16).map_err(|_| invalid())?;
+        let (name, serial) = rest.rsplit_once("s/n:").ok_or_else(invalid)?;
+        Ok(Self {
+            handle,
+            name: name.trim().to_string(),
+            serial: serial.trim().to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for Port {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Port 0x{:02X}: {}  s/n:{}", self.handle, self.name, self.serial)
+    }
+}
+
+pub fn ports(path: &str) -> Result<Vec<Port>> {
     let mut reader = csv::Reader::from_path(path)?;
-    let headers = reader.headers()?.iter();
-    let mask: Vec<bool> = headers.clone().map(|c| !c.contains("Port")).collect();
-    let filtered: Vec<_> = headers.zip(mask).filter(|(_, b)| *b).collect();
-    println!("{:#?}", filtered);
-    // let mut reader = io::BufReader::new(file);
-    // let test = reader.lines().take(2).filter_map(Result::ok);
-    // let test = test.map(|line| {
-    //     line.split("Port ").skip(1).enumerate()
-    // });
-    // println!("{:#?}", test);
+    reader
+        .headers()?
+        .iter()
+        .filter(|c| c.contains("Port"))
+        .map(str::parse)
+        .collect()
+}
+
+/// Frames of an export parsed one record at a time, so memory use does not grow with the
+/// length of the recording.
+pub struct Frames {
+    records: csv::StringRecordsIntoIter<File>,
+    layout: Vec<parse_csv::ToolColumns>,
+    row: usize,
+}
+
+impl Iterator for Frames {
+    type Item = Result<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = self.records.next()?;
+        self.row += 1;
+        Some(record.map_err(Error::from).and_then(|r| parse_csv::parse_frame(&self.layout, &r, self.row)))
+    }
+}
+
+/// Opens an export and reads its header; frames are parsed as the iterator is advanced.
+pub fn frames(path: &str) -> Result<Frames> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_path(path)?;
+    let layout = parse_csv::layout(reader.headers()?)?;
+    if layout.is_empty() {
+        return Err(Error::NoTools);
+    }
+    Ok(Frames {
+        records: reader.into_records(),
+        layout,
+        // The header is row 1
+        row: 1,
+    })
+}
+
+pub fn read(path: &str) -> Result<Vec<Frame>> {
+    frames(path)?.collect()
+}
+
+/// Writes frames in the NDI Track export layout read by [`read`]. Every frame must list the
+/// same tools in the same order; marker position blocks are not written.
+pub fn write(path: &str, frames: &[Frame]) -> Result<()> {
+    let mut writer = csv::WriterBuilder::new().flexible(true).from_path(path)?;
+    if let Some(first) = frames.first() {
+        writer.write_record(parse_csv::headers(first))?;
+    }
+    for frame in frames {
+        writer.write_record(parse_csv::format_frame(frame))?;
+    }
+    writer.flush()?;
     Ok(())
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::frame::State;
     #[test]
     fn reads_file() {
-        crate::polaris::read("data.csv").unwrap();
+        let frames = read("data.csv").unwrap();
+        assert_eq!(frames.len(), 58);
+        let femur = frames[0].tool("Y").unwrap();
+        assert!(femur.is_ok());
+        assert_eq!(femur.markers, 3);
+        assert_eq!(femur.rotation.unwrap()[0], 0.9573733);
+        assert_eq!(femur.translation.unwrap()[2], -2148.287);
+        assert_eq!(femur.error, Some(0.1576889));
+        assert_eq!(frames[0].tool("T").unwrap().port.handle, 2);
+    }
+
+    #[test]
+    fn missing_tools_have_no_pose() {
+        let headers = csv::StringRecord::from(vec![
+            "Tools", "Port 0x01: Probe s/n:1", "Frame", "Time [sec]", "Face", "State", "Q0", "Qx", "Qy", "Qz", "Tx", "Ty", "Tz", "Error", "Markers",
+        ]);
+        let record = csv::StringRecord::from(vec![
+            "1", "Port 0x01: Probe s/n:1", "10", "0.5", "1", "Missing", "", "", "", "", "", "", "", "", "0",
+        ]);
+        let layout = parse_csv::layout(&headers).unwrap();
+        let frame = parse_csv::parse_frame(&layout, &record, 2).unwrap();
+        let probe = frame.tool("Probe").unwrap();
+        assert_eq!(probe.state, State::Missing);
+        assert!(!probe.is_ok());
+        assert_eq!(probe.translation, None);
+        assert_eq!(probe.error, None);
+    }
+
+    #[test]
+    fn reports_where_parsing_failed() {
+        let headers = csv::StringRecord::from(vec![
+            "Tools", "Port 0x01: Probe s/n:1", "Frame", "Time [sec]", "Face", "State", "Q0", "Qx", "Qy", "Qz", "Tx", "Ty", "Tz", "Error", "Markers",
+        ]);
+        let record = csv::StringRecord::from(vec![
+            "1", "Port 0x01: Probe s/n:1", "10", "0.5", "1", "OK", "1", "0", "0", "0", "1.0", "x", "3.0", "0.1", "3",
+        ]);
+        let layout = parse_csv::layout(&headers).unwrap();
+        match parse_csv::parse_frame(&layout, &record, 7) {
+            Err(Error::Parse { row, column, value }) => assert_eq!((row, column, value.as_str()), (7, 12, "x")),
+            other => panic!("unexpected {other:?}"),
+        }
+        let short = csv::StringRecord::from(vec!["1", "Port 0x01: Probe s/n:1", "10"]);
+        assert!(matches!(parse_csv::parse_frame(&layout, &short, 3), Err(Error::MissingField { row: 3, column: 6 })));
+        let headers = csv::StringRecord::from(vec!["Tools", "Port 0x01: Probe s/n:1", "Frame"]);
+        assert!(matches!(parse_csv::layout(&headers), Err(Error::MissingColumn { port: 2, .. })));
+        assert!(matches!("Port 1: Probe".parse::<Port>(), Err(Error::InvalidPort(_))));
+    }
+
+    #[test]
+    fn writes_what_it_reads() {
+        let frames = read("data.csv").unwrap();
+        let path = std::env::temp_dir().join(format!("polaris-{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        write(path, &frames).unwrap();
+        assert_eq!(read(path).unwrap(), frames);
+        assert_eq!(ports(path).unwrap(), ports("data.csv").unwrap());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn streams_frames_with_row_numbers() {
+        let mut stream = frames("data.csv").unwrap();
+        assert_eq!(stream.next().unwrap().unwrap(), read("data.csv").unwrap()[0]);
+        assert_eq!(stream.count(), 57);
+
+        let path = std::env::temp_dir().join(format!("polaris-bad-{}.csv", std::process::id()));
+        let text = std::fs::read_to_string("data.csv").unwrap().replacen("0.9573733", "x", 1);
+        std::fs::write(&path, text).unwrap();
+        let results: Vec<_> = frames(path.to_str().unwrap()).unwrap().collect();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(&results[0], Err(Error::Parse { row: 2, value, .. }) if value == "x"));
+        assert!(results[1..].iter().all(Result::is_ok));
+        assert!(matches!(frames("Cargo.toml"), Err(Error::NoTools)));
+    }
+
+    #[test]
+    fn reads_ports() {
+        let ports = ports("data.csv").unwrap();
+        assert_eq!(ports.len(), 3);
+        assert_eq!(ports[0].handle, 1);
+        assert_eq!(ports[0].name, "BrainLAB Y Junction");
+        assert_eq!(ports[1].serial, "3B21FC02");
+        assert_eq!(ports[2].serial, "38220401");
     }
 }
@@ -0,0 +1,309 @@
+//! Fake tracker replaying an NDI Track export through the Combined API, for developing and
+//! testing the live path without a camera.
+//!
+//! Serves TCP clients with [`Simulator::listen`] or [`Simulator::spawn`], or any other byte
+//! stream, such as the far side of a pseudo-terminal, with [`Simulator::serve`].
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::iter::Peekable;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+use crate::combined::{check_crc, crc16, format_bx, format_tx};
+use crate::error::{Error, Result};
+use crate::frame::Frame;
+use crate::polaris::{self, Frames, Port};
+
+// Error codes sent back to the client
+const INVALID_COMMAND: &str = "ERROR01";
+const BAD_CRC: &str = "ERROR04";
+const NOT_TRACKING: &str = "ERROR0C";
+const NO_FREE_HANDLE: &str = "ERROR14";
+
+/// Replays a recording to one client at a time.
+#[derive(Debug, Clone)]
+pub struct Simulator {
+    path: String,
+    ports: Vec<Port>,
+    speed: Option<f64>,
+    looping: bool,
+}
+
+// State of one client: tracking, and the port handles it has requested
+#[derive(Default)]
+struct Client {
+    playback: Option<Playback>,
+    requested: usize,
+}
+
+// Replay position of one client
+struct Playback {
+    frames: Peekable<Frames>,
+    current: Option<Frame>,
+    started: Instant,
+    // Timestamp of the first frame in the recording
+    origin: f64,
+}
+
+fn ascii(body: &str) -> Vec<u8> {
+    format!("{body}{:04X}\r", crc16(body.as_bytes())).into_bytes()
+}
+
+// Command name and parameters, in either the `NAME params` or the `NAME:paramsCRC` form
+fn parse_command(line: &[u8]) -> std::result::Result<(&str, &str), &'static str> {
+    let line = std::str::from_utf8(line).ok().filter(|l| l.is_ascii()).ok_or(INVALID_COMMAND)?;
+    match line.split_once(':') {
+        Some((name, rest)) => {
+            let split = rest.len().checked_sub(4).ok_or(BAD_CRC)?;
+            let (parameters, crc) = rest.split_at(split);
+            let crc = u16::from_str_radix(crc, 16).map_err(|_| BAD_CRC)?;
+            check_crc(&line.as_bytes()[..line.len() - 4], crc).map_err(|_| BAD_CRC)?;
+            Ok((name, parameters))
+        }
+        None => {
+            let (name, parameters) = line.split_once(' ').unwrap_or((line, ""));
+            Ok((name, parameters.trim()))
+        }
+    }
+}
+
+impl Simulator {
+    /// Checks that `path` is a readable export; frames are read as they are served.
+    pub fn new(path: &str) -> Result<Self> {
+        polaris::frames(path)?;
+        Ok(Self {
+            path: path.to_string(),
+            ports: polaris::ports(path)?,
+            speed: None,
+            looping: false,
+        })
+    }
+    /// Follows the recording's timestamps, `speed` times faster than recorded, from `TSTART`.
+    /// Polls between two timestamps get the same frame again, as from a real tracker. Without
+    /// this every poll gets the next frame.
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        self.speed = Some(speed);
+        self
+    }
+    /// Starts over after the last frame instead of closing the connection.
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    fn start(&self) -> Result<Playback> {
+        let mut frames = polaris::frames(&self.path)?.peekable();
+        let origin = match frames.peek() {
+            Some(Ok(frame)) => frame.time().unwrap_or_default(),
+            _ => 0.0,
+        };
+        Ok(Playback {
+            frames,
+            current: None,
+            started: Instant::now(),
+            origin,
+        })
+    }
+
+    // Moves to the next frame, starting over at the end when looping; false once the
+    // recording has ended
+    fn advance(&self, playback: &mut Playback) -> Result<bool> {
+        if playback.frames.peek().is_none() && self.looping {
+            *playback = self.start()?;
+        }
+        match playback.frames.next() {
+            Some(frame) => {
+                playback.current = Some(frame?);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    // Frame for one poll, `None` once the recording has ended
+    fn poll(&self, playback: &mut Playback) -> Result<Option<Frame>> {
+        let Some(speed) = self.speed else {
+            return Ok(self.advance(playback)?.then(|| playback.current.clone()).flatten());
+        };
+        if (playback.current.is_none() || playback.frames.peek().is_none()) && !self.advance(playback)? {
+            return Ok(None);
+        }
+        let elapsed = playback.started.elapsed().as_secs_f64() * speed;
+        while let Some(next) = playback.frames.peek() {
+            // Errors are surfaced by advancing onto them
+            let due = next.as_ref().map_or(true, |n| n.time().unwrap_or_default() - playback.origin <= elapsed);
+            if !due {
+                break;
+            }
+            self.advance(playback)?;
+        }
+        Ok(playback.current.clone())
+    }
+
+    fn phsr(&self) -> String {
+        let handles: String = self.ports.iter().map(|p| format!("{:02X}031", p.handle)).collect();
+        format!("{:02X}{handles}", self.ports.len())
+    }
+
+    // Manufacturer is the first word of the port name
+    fn phinf(&self, parameters: &str) -> Option<String> {
+        let handle = u8::from_str_radix(parameters.get(..2)?, 16).ok()?;
+        let port = self.ports.iter().find(|p| p.handle == handle)?;
+        let manufacturer = port.name.split_whitespace().next().unwrap_or_default();
+        Some(format!("{:<8}{:<12.12}{:<3}{:<8.8}01", "01010000", manufacturer, "000", port.serial))
+    }
+
+    // Reply to one command, `None` to hang up
+    fn reply(&self, command: &str, parameters: &str, client: &mut Client) -> Result<Option<Vec<u8>>> {
+        let reply = match command {
+            "INIT" | "PINIT" | "PENA" | "BEEP" | "COMM" => ascii("OKAY"),
+            "TSTART" => {
+                client.playback = Some(self.start()?);
+                ascii("OKAY")
+            }
+            "TSTOP" => {
+                client.playback = None;
+                ascii("OKAY")
+            }
+            // Uploaded tools get the recording's handles in order; their definitions are ignored
+            "PHRQ" => match self.ports.get(client.requested) {
+                Some(port) => {
+                    client.requested += 1;
+                    ascii(&format!("{:02X}", port.handle))
+                }
+                None => ascii(NO_FREE_HANDLE),
+            },
+            "PVWR" => {
+                let handle = parameters.get(..2).and_then(|h| u8::from_str_radix(h, 16).ok());
+                let requested = handle.is_some_and(|h| self.ports[..client.requested].iter().any(|p| p.handle == h));
+                ascii(if requested && parameters.len() == 6 + 128 { "OKAY" } else { INVALID_COMMAND })
+            }
+            // Every tool is reported as initialised and enabled
+            "PHSR" => match parameters {
+                "02" | "03" => ascii("00"),
+                _ => ascii(&self.phsr()),
+            },
+            "PHINF" => ascii(&self.phinf(parameters).unwrap_or_else(|| INVALID_COMMAND.to_string())),
+            "TX" | "BX" => {
+                let Some(playback) = &mut client.playback else {
+                    return Ok(Some(ascii(NOT_TRACKING)));
+                };
+                let Some(frame) = self.poll(playback)? else {
+                    return Ok(None);
+                };
+                if command == "TX" { ascii(&format_tx(&frame)) } else { format_bx(&frame) }
+            }
+            _ => ascii(INVALID_COMMAND),
+        };
+        Ok(Some(reply))
+    }
+
+    /// Answers commands from one client until it disconnects or, unless looping, the
+    /// recording ends.
+    pub fn serve<S: Read + Write>(&self, stream: S) -> Result<()> {
+        let mut stream = BufReader::new(stream);
+        let mut client = Client::default();
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            stream.read_until(b'\r', &mut line)?;
+            if line.pop() != Some(b'\r') {
+                return Ok(());
+            }
+            let reply = match parse_command(&line) {
+                Ok((command, parameters)) => match self.reply(command, parameters, &mut client)? {
+                    Some(reply) => reply,
+                    None => return Ok(()),
+                },
+                Err(code) => ascii(code),
+            };
+            let writer = stream.get_mut();
+            writer.write_all(&reply)?;
+            writer.flush()?;
+        }
+    }
+
+    /// Serves clients on `listener` one after another. A client dropping the connection is
+    /// not an error; a recording that cannot be read is.
+    pub fn listen(&self, listener: TcpListener) -> Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            stream.set_nodelay(true)?;
+            match self.serve(stream) {
+                Ok(()) | Err(Error::Io(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Listens on `address` from a background thread and returns the bound address, so port 0
+    /// picks a free port.
+    pub fn spawn<A: ToSocketAddrs>(self, address: A) -> Result<(SocketAddr, JoinHandle<Result<()>>)> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        Ok((address, std::thread::spawn(move || self.listen(listener))))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::combined::{Connection, ReplyFormat};
+
+    #[test]
+    fn replays_every_frame() {
+        let (address, _) = Simulator::new("data.csv").unwrap().spawn("127.0.0.1:0").unwrap();
+        let mut connection = Connection::connect(address).unwrap();
+        assert!(matches!(connection.frame(), Err(Error::Device(code)) if code == NOT_TRACKING));
+        connection.start().unwrap();
+        assert_eq!(connection.ports()[&2].serial, "3B21FC02");
+        assert_eq!(connection.ports()[&2].name, "BrainLAB");
+
+        let frames: Vec<_> = connection.frames().collect();
+        let recorded = polaris::read("data.csv").unwrap();
+        assert_eq!(frames.len(), recorded.len() + 1);
+        // The connection closes after the last frame
+        assert!(matches!(frames.last(), Some(Err(Error::Io(_)))));
+        for (live, recorded) in frames.iter().zip(&recorded) {
+            for (a, b) in live.as_ref().unwrap().tools.iter().zip(&recorded.tools) {
+                assert_eq!((a.rotation, a.translation, a.frame), (b.rotation, b.translation, b.frame));
+            }
+        }
+    }
+
+    #[test]
+    fn follows_recorded_timestamps() {
+        let (address, _) = Simulator::new("data.csv").unwrap().with_speed(1.0).looping().spawn("127.0.0.1:0").unwrap();
+        let mut connection = Connection::connect(address).unwrap().with_format(ReplyFormat::Ascii);
+        connection.start().unwrap();
+        let numbers: Vec<u64> = connection.frames().take(20).map(|f| f.unwrap().tools[0].frame).collect();
+        // Polls come much faster than the recording's frame rate
+        assert!(numbers.windows(2).all(|w| w[0] <= w[1]));
+        assert!(numbers.windows(2).any(|w| w[0] == w[1]));
+        assert_eq!(connection.command("FOO", "").unwrap_err().to_string(), "tracker replied ERROR01");
+    }
+
+    #[test]
+    fn assigns_handles_to_uploaded_tools() {
+        use crate::tool::ToolDefinition;
+        let (address, _) = Simulator::new("data.csv").unwrap().spawn("127.0.0.1:0").unwrap();
+        let mut rom = vec![0u8; 80];
+        rom[..3].copy_from_slice(b"NDI");
+        let tool = ToolDefinition::from_rom(&rom, "Y Junction", "38220010").unwrap();
+        let mut connection = Connection::connect(address).unwrap().with_tools([tool].into_iter().collect());
+        connection.start().unwrap();
+        assert_eq!(connection.ports()[&1].name, "Y Junction");
+        assert_eq!(connection.frame().unwrap().tool("38220010").unwrap().port.handle, 1);
+        assert_eq!(connection.command("PVWR", &format!("09{:0132}", 0)).unwrap_err().to_string(), "tracker replied ERROR01");
+    }
+
+    #[test]
+    fn checks_command_crcs() {
+        assert_eq!(parse_command(b"TX 0009"), Ok(("TX", "0009")));
+        let command = format!("BX:0009{:04X}", crc16(b"BX:0009"));
+        assert_eq!(parse_command(command.as_bytes()), Ok(("BX", "0009")));
+        assert_eq!(parse_command(b"BX:00090000"), Err(BAD_CRC));
+    }
+}
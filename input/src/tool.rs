@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::polaris::Port;
+
+// Byte layout of the NDI SROM (.rom) tool description
+const ROM_MAGIC: &[u8] = b"NDI";
+const ROM_MARKER_COUNT: usize = 28;
+const ROM_MARKER_POSITIONS: usize = 72;
+const ROM_MAX_MARKERS: usize = 20;
+
+/// Nominal marker geometry of a tracked tool, expressed in the tool frame (mm).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub serial: String,
+    pub markers: Vec<[f32; 3]>,
+    /// SROM image the definition was read from, uploaded to the tracker for passive tools.
+    #[serde(skip)]
+    pub rom: Option<Vec<u8>>,
+}
+
+impl ToolDefinition {
+    pub fn from_toml(s: &str) -> Result<Self> {
+        toml::from_str(s).map_err(|e| Error::InvalidTool(e.to_string()))
+    }
+    pub fn from_rom(bytes: &[u8], name: &str, serial: &str) -> Result<Self> {
+        let invalid = |msg: &str| Error::InvalidTool(msg.to_string());
+        if !bytes.starts_with(ROM_MAGIC) {
+            return Err(invalid("not an NDI tool definition file"));
+        }
+        let count = *bytes.get(ROM_MARKER_COUNT).ok_or_else(|| invalid("truncated rom file"))? as usize;
+        if count > ROM_MAX_MARKERS {
+            return Err(invalid("too many markers in rom file"));
+        }
+        let end = ROM_MARKER_POSITIONS + count * 12;
+        let positions = bytes.get(ROM_MARKER_POSITIONS..end).ok_or_else(|| invalid("truncated rom file"))?;
+        let markers = positions
+            .chunks_exact(12)
+            .map(|m| {
+                let f = |i: usize| f32::from_le_bytes(m[i..i + 4].try_into().unwrap());
+                [f(0), f(4), f(8)]
+            })
+            .collect();
+        Ok(Self {
+            name: name.to_string(),
+            serial: serial.to_string(),
+            markers,
+            rom: Some(bytes.to_vec()),
+        })
+    }
+    /// Loads a `.rom` binary or a TOML definition depending on the file extension.
+    /// A `.rom` carries no serial number, so the file stem is used for both name and serial.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("rom") => {
+                let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+                Self::from_rom(&std::fs::read(path)?, stem, stem)
+            }
+            _ => Self::from_toml(&std::fs::read_to_string(path)?),
+        }
+    }
+}
+
+/// Tool definitions keyed by serial number.
+#[derive(Debug, Default, Clone)]
+pub struct ToolLibrary {
+    tools: HashMap<String, ToolDefinition>,
+}
+
+impl ToolLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn insert(&mut self, tool: ToolDefinition) -> Option<ToolDefinition> {
+        self.tools.insert(tool.serial.clone(), tool)
+    }
+    pub fn get(&self, serial: &str) -> Option<&ToolDefinition> {
+        self.tools.get(serial)
+    }
+    pub fn for_port(&self, port: &Port) -> Option<&ToolDefinition> {
+        self.get(&port.serial)
+    }
+    /// Every definition, in serial number order.
+    pub fn iter(&self) -> impl Iterator<Item = &ToolDefinition> {
+        let mut tools: Vec<_> = self.tools.values().collect();
+        tools.sort_by(|a, b| a.serial.cmp(&b.serial));
+        tools.into_iter()
+    }
+}
+
+impl FromIterator<ToolDefinition> for ToolLibrary {
+    fn from_iter<I: IntoIterator<Item = ToolDefinition>>(iter: I) -> Self {
+        let mut library = Self::new();
+        for tool in iter {
+            library.insert(tool);
+        }
+        library
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_toml_definition() {
+        let tool = ToolDefinition::from_toml(
+            r#"
+            name = "BrainLAB Y Junction"
+            serial = "38220010"
+            markers = [[0.0, 0.0, 0.0], [-55.9, -72.3, -20.5], [-85.8, 3.9, -50.2]]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(tool.serial, "38220010");
+        assert_eq!(tool.markers.len(), 3);
+        assert_eq!(tool.markers[1], [-55.9, -72.3, -20.5]);
+    }
+
+    #[test]
+    fn parses_rom_markers() {
+        let mut rom = vec![0u8; 752];
+        rom[..3].copy_from_slice(ROM_MAGIC);
+        rom[ROM_MARKER_COUNT] = 2;
+        for (i, v) in [1.0f32, 2.0, 3.0, -4.0, 5.5, 6.0].iter().enumerate() {
+            let at = ROM_MARKER_POSITIONS + i * 4;
+            rom[at..at + 4].copy_from_slice(&v.to_le_bytes());
+        }
+        let tool = ToolDefinition::from_rom(&rom, "probe", "38220401").unwrap();
+        assert_eq!(tool.markers, vec![[1.0, 2.0, 3.0], [-4.0, 5.5, 6.0]]);
+        assert_eq!(tool.rom, Some(rom));
+        assert!(ToolDefinition::from_rom(b"XYZ", "probe", "0").is_err());
+    }
+
+    #[test]
+    fn links_tools_to_ports() {
+        let port: Port = "Port 0x02: BrainLAB T Junction  s/n:3B21FC02".parse().unwrap();
+        let library: ToolLibrary = [ToolDefinition {
+            name: "T Junction".into(),
+            serial: "3B21FC02".into(),
+            markers: vec![[0.0; 3]; 3],
+            rom: None,
+        }]
+        .into_iter()
+        .collect();
+        assert_eq!(library.for_port(&port).unwrap().name, "T Junction");
+    }
+}
@@ -0,0 +1,237 @@
+use nalgebra as na;
+
+use crate::mesh::{Mesh, Registration};
+use crate::series::PoseSeries;
+use crate::transform::{gT, IsFrameOfReference, Transform};
+use crate::{Error, Model, Result};
+
+// Interior points of the wrapped path and relaxation sweeps
+const WRAP_POINTS: usize = 48;
+const WRAP_ITERATIONS: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Ligament {
+    Acl,
+    Pcl,
+    Mcl,
+    Lcl,
+}
+
+/// Ligament spanning from a point fixed in bone `A` to a point fixed in bone `B`,
+/// e.g. femoral origin to tibial insertion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LigamentPath<A: IsFrameOfReference, B: IsFrameOfReference> {
+    pub ligament: Ligament,
+    pub origin: na::Point3<f32>,
+    pub insertion: na::Point3<f32>,
+    frames: std::marker::PhantomData<(A, B)>,
+}
+
+impl<A: IsFrameOfReference, B: IsFrameOfReference> LigamentPath<A, B> {
+    /// `origin` is given in the frame of `A`, `insertion` in the frame of `B`.
+    pub fn new(ligament: Ligament, origin: na::Point3<f32>, insertion: na::Point3<f32>) -> Self {
+        Self {
+            ligament,
+            origin,
+            insertion,
+            frames: std::marker::PhantomData,
+        }
+    }
+
+    // Both attachment points in the frame of A
+    fn endpoints(&self, first: &gT<A>, second: &gT<B>) -> (na::Point3<f32>, na::Point3<f32>) {
+        let a_from_b: Transform<A, B> = first.rigid_inverse() * *second;
+        (self.origin, a_from_b.transform_point(&self.insertion))
+    }
+
+    /// Straight-line insertion-to-insertion distance in mm.
+    pub fn length(&self, first: &gT<A>, second: &gT<B>) -> f32 {
+        let (start, end) = self.endpoints(first, second);
+        (end - start).norm()
+    }
+
+    /// Length of the shortest path that passes through none of the registered models. Fails if
+    /// a registration cannot be inverted.
+    pub fn wrapped_length(&self, first: &gT<A>, second: &gT<B>, obstacles: &Obstacles<A, B>) -> Result<f32> {
+        let (start, end) = self.endpoints(first, second);
+        if obstacles.first.is_none() && obstacles.second.is_none() {
+            return Ok((end - start).norm());
+        }
+        let a_from_b: Transform<A, B> = first.rigid_inverse() * *second;
+        // Each model with the transforms into and out of its frame from that of A
+        let first_model = match obstacles.first {
+            Some((mesh, registration)) => Some((mesh, registration.transform.inverse()?, registration.transform)),
+            None => None,
+        };
+        let second_model = match obstacles.second {
+            Some((mesh, registration)) => {
+                let from_model = a_from_b * registration.transform;
+                Some((mesh, from_model.inverse()?, from_model))
+            }
+            None => None,
+        };
+        shrink_wrap(&start, &end, |p| {
+            let mut p = *p;
+            if let Some((mesh, to_model, from_model)) = &first_model {
+                p = push_out(*mesh, to_model, from_model, &p)?;
+            }
+            if let Some((mesh, to_model, from_model)) = &second_model {
+                p = push_out(*mesh, to_model, from_model, &p)?;
+            }
+            Ok(p)
+        })
+    }
+
+    /// Length in every frame where both bones were tracked, wrapped around any obstacles.
+    pub fn track(&self, first: &PoseSeries<A>, second: &PoseSeries<B>, obstacles: &Obstacles<A, B>) -> Result<Vec<Option<f32>>> {
+        if first.len() != second.len() {
+            return Err(Error::LengthMismatch { expected: first.len(), found: second.len() });
+        }
+        first
+            .poses()
+            .iter()
+            .zip(second.poses())
+            .map(|(a, b)| match (a, b) {
+                (Some(a), Some(b)) => self.wrapped_length(a, b, obstacles).map(Some),
+                _ => Ok(None),
+            })
+            .collect()
+    }
+
+    /// Lengths relative to the length in the reference pose of the two bones, e.g. from a static
+    /// trial, measured around the same obstacles (1.0 = reference length).
+    pub fn normalise(
+        &self,
+        lengths: &[Option<f32>],
+        reference: (&gT<A>, &gT<B>),
+        obstacles: &Obstacles<A, B>,
+    ) -> Result<Vec<Option<f32>>> {
+        let reference = self.wrapped_length(reference.0, reference.1, obstacles)?;
+        Ok(lengths.iter().map(|l| l.map(|l| l / reference)).collect())
+    }
+}
+
+/// Registered bone models a ligament wraps around, on either or both bones.
+#[derive(Debug)]
+pub struct Obstacles<'a, A: IsFrameOfReference, B: IsFrameOfReference> {
+    pub first: Option<(&'a Mesh<Model<A>>, &'a Registration<A>)>,
+    pub second: Option<(&'a Mesh<Model<B>>, &'a Registration<B>)>,
+}
+
+impl<'a, A: IsFrameOfReference, B: IsFrameOfReference> Obstacles<'a, A, B> {
+    /// No obstacles: lengths are straight lines.
+    pub fn new() -> Self {
+        Self { first: None, second: None }
+    }
+    pub fn with_first(mut self, mesh: &'a Mesh<Model<A>>, registration: &'a Registration<A>) -> Self {
+        self.first = Some((mesh, registration));
+        self
+    }
+    pub fn with_second(mut self, mesh: &'a Mesh<Model<B>>, registration: &'a Registration<B>) -> Self {
+        self.second = Some((mesh, registration));
+        self
+    }
+}
+
+impl<A: IsFrameOfReference, B: IsFrameOfReference> Default for Obstacles<'_, A, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Moves a point inside the model back onto its surface, in the frame of the path
+fn push_out<F: IsFrameOfReference, M: IsFrameOfReference>(
+    mesh: &Mesh<M>,
+    to_model: &Transform<M, F>,
+    from_model: &Transform<F, M>,
+    p: &na::Point3<f32>,
+) -> Result<na::Point3<f32>> {
+    let (surface, distance) = mesh.signed_closest_point(&to_model.transform_point(p))?;
+    Ok(if distance < 0.0 { from_model.transform_point(&surface) } else { *p })
+}
+
+/// Shrink-wraps a polyline between `start` and `end` over the outside of `mesh`: interior points
+/// are repeatedly relaxed towards their neighbours and pushed back onto the surface. Fails on a
+/// mesh without faces.
+pub fn wrapped_length<F: IsFrameOfReference>(mesh: &Mesh<F>, start: &na::Point3<f32>, end: &na::Point3<f32>) -> Result<f32> {
+    shrink_wrap(start, end, |p| {
+        let (surface, distance) = mesh.signed_closest_point(p)?;
+        Ok(if distance < 0.0 { surface } else { *p })
+    })
+}
+
+fn shrink_wrap(
+    start: &na::Point3<f32>,
+    end: &na::Point3<f32>,
+    push_out: impl Fn(&na::Point3<f32>) -> Result<na::Point3<f32>>,
+) -> Result<f32> {
+    let n = WRAP_POINTS + 2;
+    let mut path: Vec<na::Point3<f32>> = (0..n).map(|i| start + (end - start) * (i as f32 / (n - 1) as f32)).collect();
+    for _ in 0..WRAP_ITERATIONS {
+        for i in 1..n - 1 {
+            path[i] = push_out(&na::center(&path[i - 1], &path[i + 1]))?;
+        }
+    }
+    Ok(path.windows(2).map(|w| (w[1] - w[0]).norm()).sum())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bone_to_tracker::{Femur, Tibia};
+    use crate::mesh::ellipsoid;
+
+    fn at<RB: IsFrameOfReference>(x: f32, y: f32, z: f32) -> gT<RB> {
+        Transform::from_parts(&na::Vector3::new(x, y, z), &na::UnitQuaternion::identity())
+    }
+
+    #[test]
+    fn tracks_straight_length() {
+        let acl = LigamentPath::<Femur, Tibia>::new(Ligament::Acl, na::Point3::new(0.0, 0.0, -10.0), na::Point3::new(0.0, 10.0, 5.0));
+        let femur = PoseSeries::new(vec![0.0, 1.0, 2.0], vec![Some(at(0.0, 0.0, 0.0)); 3]).unwrap();
+        let tibia = PoseSeries::new(vec![0.0, 1.0, 2.0], vec![Some(at(0.0, -10.0, -35.0)), Some(at(0.0, -10.0, -45.0)), None]).unwrap();
+        let lengths = acl.track(&femur, &tibia, &Obstacles::new()).unwrap();
+        assert_eq!(lengths, vec![Some(20.0), Some(30.0), None]);
+        let reference = (&at(0.0, 0.0, 0.0), &at(0.0, -10.0, -35.0));
+        assert_eq!(acl.normalise(&lengths, reference, &Obstacles::new()).unwrap(), vec![Some(1.0), Some(1.5), None]);
+    }
+
+    #[test]
+    fn wraps_around_the_model() {
+        let sphere = ellipsoid::<Model<Femur>>(10.0, 10.0, 10.0, 48);
+        let identity = Transform::from_parts(&na::Vector3::zeros(), &na::UnitQuaternion::identity());
+        let registration = Registration { transform: identity, rms: 0.0, iterations: 0 };
+        let mcl = LigamentPath::<Femur, Tibia>::new(Ligament::Mcl, na::Point3::new(-20.0, 0.0, 5.0), na::Point3::new(20.0, 0.0, 5.0));
+        let (femur, tibia) = (at(0.0, 0.0, 0.0), at(0.0, 0.0, 0.0));
+        assert_relative_eq!(mcl.length(&femur, &tibia), 40.0);
+        // Two tangents and the arc between them on a circle of radius 10
+        let tangent = (20f32.powi(2) + 5f32.powi(2) - 100.0).sqrt();
+        let angle = (-375.0f32 / 425.0).acos() - 2.0 * (10.0 / 425f32.sqrt()).acos();
+        let expected = 2.0 * tangent + 10.0 * angle;
+        let obstacles = Obstacles::new().with_first(&sphere, &registration);
+        assert_relative_eq!(mcl.wrapped_length(&femur, &tibia, &obstacles).unwrap(), expected, epsilon = 0.3);
+    }
+
+    #[test]
+    fn wraps_around_both_bones() {
+        // Femoral and tibial spheres side by side, both crossed by the straight path
+        fn offset<RB: IsFrameOfReference>(x: f32) -> Registration<RB> {
+            let transform = Transform::from_parts(&na::Vector3::new(x, 0.0, 0.0), &na::UnitQuaternion::identity());
+            Registration { transform, rms: 0.0, iterations: 0 }
+        }
+        let (condyle, plateau) = (ellipsoid::<Model<Femur>>(10.0, 10.0, 10.0, 24), ellipsoid::<Model<Tibia>>(10.0, 10.0, 10.0, 24));
+        let (on_femur, on_tibia) = (offset(-12.0), offset(12.0));
+        let mcl = LigamentPath::<Femur, Tibia>::new(Ligament::Mcl, na::Point3::new(-30.0, 0.0, 3.0), na::Point3::new(30.0, 0.0, 3.0));
+        let (femur, tibia) = (at(0.0, 0.0, 0.0), at(0.0, 0.0, 0.0));
+
+        let straight = mcl.length(&femur, &tibia);
+        let first = mcl.wrapped_length(&femur, &tibia, &Obstacles::new().with_first(&condyle, &on_femur)).unwrap();
+        let second = mcl.wrapped_length(&femur, &tibia, &Obstacles::new().with_second(&plateau, &on_tibia)).unwrap();
+        let both = Obstacles::new().with_first(&condyle, &on_femur).with_second(&plateau, &on_tibia);
+        let wrapped = mcl.wrapped_length(&femur, &tibia, &both).unwrap();
+        assert!(first > straight + 1.0);
+        assert_relative_eq!(first, second, epsilon = 0.1);
+        // The path bridges the gap between the bones, so the detours do not simply add up
+        assert!(wrapped > first + 0.5 && wrapped < straight + 2.0 * (first - straight));
+    }
+}
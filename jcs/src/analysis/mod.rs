@@ -0,0 +1,9 @@
+pub mod ligament;
+pub mod neutral;
+pub mod sensitivity;
+pub mod uncertainty;
+
+pub use ligament::{Ligament, LigamentPath, Obstacles};
+pub use neutral::{NeutralMode, NeutralPose};
+pub use sensitivity::{sensitivity, Sensitivity, SensitivityTable};
+pub use uncertainty::{Interval, Propagation, Uncertainty};
@@ -0,0 +1,198 @@
+use nalgebra as na;
+
+use crate::bone_to_tracker::{Kinematics, Motion, Side};
+use crate::series::PoseSeries;
+use crate::solvers::Solver;
+use crate::transform::{gT, IsFrameOfReference, Mldivide, Transform};
+use crate::{Error, Result};
+
+/// How a trial is expressed relative to the neutral pose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NeutralMode {
+    /// Subtract the mean reference `Motion` from every frame.
+    SubtractAngles,
+    /// Rotate the anatomical frame of the second bone so that it is aligned with the first in the
+    /// reference trial, then solve again. Angles are zero at neutral; translations keep the
+    /// anatomical origins.
+    RedefineFrames,
+}
+
+/// Neutral pose measured in a static reference trial, e.g. standing in extension.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NeutralPose {
+    motion: Motion,
+    /// Mean orientation of the second bone in the first.
+    rotation: na::UnitQuaternion<f32>,
+}
+
+impl NeutralPose {
+    /// Mean motion and relative orientation over the frames where both bones were tracked;
+    /// `None` if there are none. Fails if the series differ in length.
+    pub fn from_poses<S: Solver>(
+        solver: &S,
+        first: &PoseSeries<S::F>,
+        second: &PoseSeries<S::T>,
+        side: Side,
+    ) -> Result<Option<Self>> {
+        check_lengths(first, second)?;
+        let pairs: Vec<_> = first
+            .poses()
+            .iter()
+            .zip(second.poses())
+            .filter_map(|(a, b)| Some((*a.as_ref()?, *b.as_ref()?)))
+            .collect();
+        let Some(motion) = pairs.iter().map(|(a, b)| Some(solver.solve(*a, *b, side))).collect::<Kinematics>().mean() else {
+            return Ok(None);
+        };
+        let relative: Vec<_> = pairs
+            .iter()
+            .map(|(a, b)| {
+                let relative: Transform<S::F, S::T> = a.rigid_inverse() * *b;
+                relative.rotation()
+            })
+            .collect();
+        Ok(Some(Self {
+            motion,
+            rotation: mean_rotation(&relative),
+        }))
+    }
+    pub fn motion(&self) -> &Motion {
+        &self.motion
+    }
+    pub fn rotation(&self) -> &na::UnitQuaternion<f32> {
+        &self.rotation
+    }
+    /// Kinematics of a trial relative to this neutral pose. Fails if the series differ in length.
+    pub fn apply<S: Solver>(
+        &self,
+        mode: NeutralMode,
+        solver: &S,
+        first: &PoseSeries<S::F>,
+        second: &PoseSeries<S::T>,
+        side: Side,
+    ) -> Result<Kinematics> {
+        check_lengths(first, second)?;
+        let offset = self.rotation.inverse();
+        Ok(first
+            .poses()
+            .iter()
+            .zip(second.poses())
+            .map(|(a, b)| {
+                let (a, b) = (*a.as_ref()?, *b.as_ref()?);
+                Some(match mode {
+                    NeutralMode::SubtractAngles => subtract(&solver.solve(a, b, side), &self.motion),
+                    NeutralMode::RedefineFrames => solver.solve(a, redefine(&b, &offset), side),
+                })
+            })
+            .collect())
+    }
+}
+
+fn check_lengths<A: IsFrameOfReference, B: IsFrameOfReference>(first: &PoseSeries<A>, second: &PoseSeries<B>) -> Result<()> {
+    if first.len() != second.len() {
+        return Err(Error::LengthMismatch { expected: first.len(), found: second.len() });
+    }
+    Ok(())
+}
+
+impl Kinematics {
+    /// Mean of every degree of freedom over the tracked frames; `None` if there are none.
+    pub fn mean(&self) -> Option<Motion> {
+        let tracked: Vec<_> = self.frames().iter().flatten().collect();
+        if tracked.is_empty() {
+            return None;
+        }
+        let mut sum = [0.0; 6];
+        for motion in &tracked {
+            sum.iter_mut().zip(motion.dofs()).for_each(|(s, d)| *s += d);
+        }
+        Some(Motion::from_dofs(sum.map(|s| s / tracked.len() as f32)))
+    }
+    /// Every frame with `reference` subtracted.
+    pub fn relative_to(&self, reference: &Motion) -> Self {
+        self.frames().iter().map(|m| m.map(|m| subtract(&m, reference))).collect()
+    }
+}
+
+fn subtract(motion: &Motion, reference: &Motion) -> Motion {
+    let (mut dofs, reference) = (motion.dofs(), reference.dofs());
+    dofs.iter_mut().zip(reference).for_each(|(d, r)| *d -= r);
+    Motion::from_dofs(dofs)
+}
+
+// Rotates the anatomical axes about the unchanged origin
+fn redefine<B: IsFrameOfReference>(pose: &gT<B>, offset: &na::UnitQuaternion<f32>) -> gT<B> {
+    Transform::from_parts(&pose.origin().coords, &(pose.rotation() * offset))
+}
+
+// Normalised sum of quaternions on the same hemisphere as the first
+fn mean_rotation(rotations: &[na::UnitQuaternion<f32>]) -> na::UnitQuaternion<f32> {
+    let first = rotations[0].coords;
+    let sum: na::Vector4<f32> = rotations
+        .iter()
+        .map(|q| if q.coords.dot(&first) < 0.0 { -q.coords } else { q.coords })
+        .sum();
+    na::UnitQuaternion::from_quaternion(na::Quaternion::from(sum))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bone_to_tracker::{Femur, Tibia};
+    use crate::solvers::GroodAndSuntay;
+
+    fn pose<RB: IsFrameOfReference>(z: f32, rotation: na::UnitQuaternion<f32>) -> gT<RB> {
+        Transform::from_parts(&na::Vector3::new(0.0, 0.0, z), &rotation)
+    }
+
+    fn series<RB: IsFrameOfReference>(poses: Vec<Option<gT<RB>>>) -> PoseSeries<RB> {
+        PoseSeries::new((0..poses.len()).map(|i| i as f64).collect(), poses).unwrap()
+    }
+
+    #[test]
+    fn zeroes_the_reference_trial() {
+        let solver = GroodAndSuntay::tibiofemoral();
+        // Tibia slightly flexed and rotated relative to the femur while standing
+        let femur_rotation = na::UnitQuaternion::from_scaled_axis(na::Vector3::z() * 0.2);
+        let offset = na::UnitQuaternion::from_euler_angles(0.08, 0.03, -0.05);
+        let femur = series::<Femur>(vec![Some(pose(0.0, femur_rotation)); 3]);
+        let tibia = series::<Tibia>(vec![Some(pose(-400.0, femur_rotation * offset)), None, Some(pose(-400.0, femur_rotation * offset))]);
+        let neutral = NeutralPose::from_poses(&solver, &femur, &tibia, Side::Right).unwrap().unwrap();
+        assert_relative_eq!(neutral.rotation().angle_to(&offset), 0.0, epsilon = 1e-3);
+        let short = series::<Tibia>(vec![None]);
+        assert!(matches!(NeutralPose::from_poses(&solver, &femur, &short, Side::Right), Err(Error::LengthMismatch { expected: 3, found: 1 })));
+
+        let subtracted = neutral.apply(NeutralMode::SubtractAngles, &solver, &femur, &tibia, Side::Right).unwrap();
+        assert!(subtracted.frames()[1].is_none());
+        for dof in subtracted.frames()[0].unwrap().dofs() {
+            assert_relative_eq!(dof, 0.0, epsilon = 1e-3);
+        }
+        let redefined = neutral.apply(NeutralMode::RedefineFrames, &solver, &femur, &tibia, Side::Right).unwrap();
+        let motion = redefined.frames()[2].unwrap();
+        for angle in [motion.flexion(), motion.external(), motion.varus()] {
+            assert_relative_eq!(angle, 0.0, epsilon = 0.1);
+        }
+    }
+
+    #[test]
+    fn measures_flexion_from_neutral() {
+        let solver = GroodAndSuntay::tibiofemoral();
+        // Flexion turns the tibia backwards, i.e. negatively about the mediolateral axis
+        let flexed_by = |degrees: f32| na::UnitQuaternion::from_scaled_axis(na::Vector3::x() * -degrees.to_radians());
+        let femur = series::<Femur>(vec![Some(pose(0.0, flexed_by(0.0))); 2]);
+        let standing = series::<Tibia>(vec![Some(pose(-400.0, flexed_by(5.0))); 2]);
+        let neutral = NeutralPose::from_poses(&solver, &femur, &standing, Side::Right).unwrap().unwrap();
+        assert_relative_eq!(neutral.motion().flexion(), 5.0, epsilon = 1e-2);
+
+        let flexed = pose(-400.0, flexed_by(45.0));
+        let tibia = series::<Tibia>(vec![Some(flexed); 2]);
+        let expected = solver.solve(femur.poses()[0].unwrap(), flexed, Side::Right).flexion() - neutral.motion().flexion();
+        assert_relative_eq!(expected, 40.0, epsilon = 1e-2);
+        for mode in [NeutralMode::SubtractAngles, NeutralMode::RedefineFrames] {
+            let flexion = neutral.apply(mode, &solver, &femur, &tibia, Side::Right).unwrap().frames()[0].unwrap().flexion();
+            assert_relative_eq!(flexion, expected, epsilon = 1e-2);
+        }
+        let zeroed = Kinematics::from_iter([Some(*neutral.motion())]).relative_to(neutral.motion());
+        assert_relative_eq!(zeroed.mean().unwrap().flexion(), 0.0);
+    }
+}
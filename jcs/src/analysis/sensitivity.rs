@@ -0,0 +1,133 @@
+use std::fmt;
+
+use super::uncertainty::{Chain, PARAMETERS, PER_BONE};
+use crate::bone_to_tracker::{DefinedTracker, Motion};
+use crate::solvers::Solver;
+use crate::transform::gT;
+use crate::{RigidBody, Tracker};
+
+// Central difference step in mm
+const STEP: f32 = 0.5;
+const LANDMARKS: [&str; 3] = ["medial", "lateral", "proximal_distal"];
+const AXES: [&str; 3] = ["i", "j", "k"];
+
+/// Change of every degree of freedom per mm of error in one landmark along one anatomical axis
+/// of its bone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sensitivity {
+    pub bone: String,
+    pub landmark: &'static str,
+    pub axis: &'static str,
+    /// Degrees or mm per mm, in the order of [`Motion::DOF_NAMES`].
+    pub per_mm: [f32; 6],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensitivityTable {
+    pub rows: Vec<Sensitivity>,
+}
+
+impl SensitivityTable {
+    /// Landmarks ordered from most to least influential on `dof`, with the change per mm of
+    /// error in the worst direction.
+    pub fn ranking(&self, dof: usize) -> Vec<(&str, &'static str, f32)> {
+        let mut ranking: Vec<(&str, &'static str, f32)> = Vec::new();
+        for chunk in self.rows.chunks(AXES.len()) {
+            let magnitude = chunk.iter().map(|r| r.per_mm[dof].powi(2)).sum::<f32>().sqrt();
+            ranking.push((&chunk[0].bone, chunk[0].landmark, magnitude));
+        }
+        ranking.sort_by(|a, b| b.2.total_cmp(&a.2));
+        ranking
+    }
+}
+
+impl fmt::Display for SensitivityTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<8}{:<16}{:<6}", "bone", "landmark", "axis")?;
+        for name in Motion::DOF_NAMES {
+            write!(f, "{name:>10}")?;
+        }
+        writeln!(f)?;
+        for row in &self.rows {
+            write!(f, "{:<8}{:<16}{:<6}", row.bone, row.landmark, row.axis)?;
+            for value in row.per_mm {
+                write!(f, "{value:>10.3}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Sensitivity of the motion in the frame with the trackers at `poses` to each probed landmark,
+/// perturbed along the anatomical axes of its bone. `names` label the two bones in the table.
+pub fn sensitivity<S, const A: usize, const B: usize>(
+    solver: &S,
+    first: &RigidBody<A>,
+    second: &RigidBody<B>,
+    poses: (gT<Tracker<RigidBody<A>>>, gT<Tracker<RigidBody<B>>>),
+    names: [&str; 2],
+) -> SensitivityTable
+where
+    S: Solver<F = RigidBody<A>, T = RigidBody<B>>,
+    RigidBody<A>: DefinedTracker,
+    RigidBody<B>: DefinedTracker,
+{
+    let chain = Chain { solver, first, second, poses };
+    let (a, b) = (first.in_global(), second.in_global());
+    let frames = [[a.i(), a.j(), a.k()], [b.i(), b.j(), b.k()]];
+    let mut rows = Vec::new();
+    for (bone, (name, axes)) in names.iter().zip(&frames).enumerate() {
+        for (l, landmark) in LANDMARKS.iter().enumerate() {
+            for (axis, direction) in AXES.iter().zip(axes) {
+                let offset = bone * PER_BONE + 3 * l;
+                let mut delta = [0.0; PARAMETERS];
+                delta[offset..offset + 3].copy_from_slice((direction * STEP).as_slice());
+                let plus = chain.solve(&delta).dofs();
+                delta[offset..offset + 3].copy_from_slice((direction * -STEP).as_slice());
+                let minus = chain.solve(&delta).dofs();
+                rows.push(Sensitivity {
+                    bone: name.to_string(),
+                    landmark,
+                    axis,
+                    per_mm: std::array::from_fn(|dof| (plus[dof] - minus[dof]) / (2.0 * STEP)),
+                });
+            }
+        }
+    }
+    SensitivityTable { rows }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bone_to_tracker::{Femur, Side, Tibia};
+    use crate::data::Datum;
+    use crate::solvers::GroodAndSuntay;
+    use crate::synthetic::SyntheticKnee;
+
+    #[test]
+    fn epicondyles_drive_varus() {
+        let knee = SyntheticKnee::new(Side::Right);
+        let (femur, tibia): (Femur, Tibia) = knee.rigid_bodies(&Motion::from_dofs([0.0; 6])).unwrap();
+        let frame = &knee.frames(&[Motion::from_dofs([20.0, 0.0, 0.0, 0.0, 0.0, 0.0])]).unwrap()[0];
+        let poses = (
+            Datum::from_frame(frame, "Y").unwrap().to_transform(),
+            Datum::from_frame(frame, "T").unwrap().to_transform(),
+        );
+        let table = sensitivity(&GroodAndSuntay::tibiofemoral(), &femur, &tibia, poses, ["femur", "tibia"]);
+        assert_eq!(table.rows.len(), 18);
+
+        // Raising one epicondyle tilts the femoral i axis: 1 mm over an 80 mm epicondylar
+        // width is about 0.7 degrees of varus, and moving along i changes nothing
+        let medial_k = &table.rows[2];
+        assert_eq!((medial_k.bone.as_str(), medial_k.landmark, medial_k.axis), ("femur", "medial", "k"));
+        assert_relative_eq!(medial_k.per_mm[2].abs(), (1.0f32 / 80.0).atan().to_degrees(), epsilon = 0.05);
+        assert_relative_eq!(table.rows[0].per_mm[2], 0.0, epsilon = 1e-2);
+
+        let varus = table.ranking(2);
+        assert!(["medial", "lateral"].contains(&varus[0].1));
+        assert!(varus[0].2 > varus.last().unwrap().2);
+        assert_eq!(table.to_string().lines().count(), 19);
+    }
+}
@@ -0,0 +1,265 @@
+use nalgebra as na;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rand_distr::{Distribution, StandardNormal};
+
+use crate::bone_to_tracker::{DefinedTracker, Landmark, Motion, Orientation};
+use crate::solvers::Solver;
+use crate::transform::{gT, IsFrameOfReference, Transform};
+use crate::{Error, Result, RigidBody, Tracker};
+
+// Three landmarks, the tracker at digitisation and the tracker in the frame, per bone
+pub(super) const PER_BONE: usize = 21;
+pub(super) const PARAMETERS: usize = 2 * PER_BONE;
+// Two-sided 95% interval of a normal distribution
+const Z_95: f32 = 1.959_964;
+// Central difference step, in standard deviations
+const STEP: f32 = 0.1;
+
+/// Measurement errors as standard deviations per axis.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Uncertainty {
+    /// Digitisation error of each probed landmark in mm.
+    pub landmark: f32,
+    /// Tracker position error in mm, both when digitising and during the trial.
+    pub tracker_translation: f32,
+    /// Tracker orientation error in degrees.
+    pub tracker_rotation: f32,
+}
+
+/// Spread of one degree of freedom.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub mean: f32,
+    pub std: f32,
+    /// Bounds of the 95% confidence interval.
+    pub lower: f32,
+    pub upper: f32,
+}
+
+/// Motion with confidence intervals, in the order of [`Motion::DOF_NAMES`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Propagation {
+    pub nominal: Motion,
+    pub dofs: [Interval; 6],
+    pub covariance: na::Matrix6<f32>,
+}
+
+// Everything the motion of one frame depends on
+pub(super) struct Chain<'a, S, const A: usize, const B: usize> {
+    pub(super) solver: &'a S,
+    pub(super) first: &'a RigidBody<A>,
+    pub(super) second: &'a RigidBody<B>,
+    pub(super) poses: (gT<Tracker<RigidBody<A>>>, gT<Tracker<RigidBody<B>>>),
+}
+
+impl<S, const A: usize, const B: usize> Chain<'_, S, A, B>
+where
+    S: Solver<F = RigidBody<A>, T = RigidBody<B>>,
+    RigidBody<A>: DefinedTracker,
+    RigidBody<B>: DefinedTracker,
+{
+    /// Motion with every parameter offset by `delta` (mm and radians).
+    pub(super) fn solve(&self, delta: &[f32; PARAMETERS]) -> Motion {
+        let (first, first_pose) = perturb(self.first, &self.poses.0, &delta[..PER_BONE]);
+        let (second, second_pose) = perturb(self.second, &self.poses.1, &delta[PER_BONE..]);
+        self.solver.solve(first.take_pose(first_pose), second.take_pose(second_pose), first.side)
+    }
+}
+
+fn vector(delta: &[f32]) -> na::Vector3<f32> {
+    na::Vector3::new(delta[0], delta[1], delta[2])
+}
+
+// Small rotation about global axes followed by a translation
+fn perturb_pose<A: IsFrameOfReference, B: IsFrameOfReference>(pose: &Transform<A, B>, delta: &[f32]) -> Transform<A, B> {
+    let translation = pose.translation().coords + vector(&delta[..3]);
+    let rotation = na::UnitQuaternion::from_scaled_axis(vector(&delta[3..])) * pose.rotation();
+    Transform::from_parts(&translation, &rotation)
+}
+
+fn perturb_landmark<RB: IsFrameOfReference, O: Orientation>(landmark: &Landmark<RB, O>, delta: &[f32]) -> Landmark<RB, O> {
+    let data = landmark.probe_data();
+    Landmark::new("", "", data.with_pose(data.translation() + vector(delta), *data.rotation()))
+}
+
+fn perturb<const ID: usize>(
+    body: &RigidBody<ID>,
+    pose: &gT<Tracker<RigidBody<ID>>>,
+    delta: &[f32],
+) -> (RigidBody<ID>, gT<Tracker<RigidBody<ID>>>) {
+    let body = RigidBody {
+        side: body.side,
+        medial: perturb_landmark(&body.medial, &delta[0..3]),
+        lateral: perturb_landmark(&body.lateral, &delta[3..6]),
+        proximal_distal: perturb_landmark(&body.proximal_distal, &delta[6..9]),
+        tracker: perturb_pose(&body.tracker, &delta[9..15]),
+        model: None,
+    };
+    (body, perturb_pose(pose, &delta[15..21]))
+}
+
+impl Uncertainty {
+    // Standard deviation of every parameter, in mm and radians
+    fn sigmas(&self) -> [f32; PARAMETERS] {
+        let mut bone = [self.landmark; PER_BONE];
+        for pose in [9, 15] {
+            bone[pose..pose + 3].fill(self.tracker_translation);
+            bone[pose + 3..pose + 6].fill(self.tracker_rotation.to_radians());
+        }
+        let mut sigmas = [0.0; PARAMETERS];
+        sigmas[..PER_BONE].copy_from_slice(&bone);
+        sigmas[PER_BONE..].copy_from_slice(&bone);
+        sigmas
+    }
+
+    /// Intervals from `samples` random perturbations of the landmarks and trackers for the frame
+    /// with the trackers at `poses`; fails with fewer than two samples.
+    pub fn monte_carlo<S, const A: usize, const B: usize>(
+        &self,
+        solver: &S,
+        first: &RigidBody<A>,
+        second: &RigidBody<B>,
+        poses: (gT<Tracker<RigidBody<A>>>, gT<Tracker<RigidBody<B>>>),
+        samples: usize,
+        seed: u64,
+    ) -> Result<Propagation>
+    where
+        S: Solver<F = RigidBody<A>, T = RigidBody<B>>,
+        RigidBody<A>: DefinedTracker,
+        RigidBody<B>: DefinedTracker,
+    {
+        if samples < 2 {
+            return Err(Error::TooFewSamples(samples));
+        }
+        let chain = Chain { solver, first, second, poses };
+        let sigmas = self.sigmas();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let motions: Vec<na::Vector6<f32>> = (0..samples)
+            .map(|_| {
+                let delta = sigmas.map(|s| {
+                    let z: f32 = StandardNormal.sample(&mut rng);
+                    s * z
+                });
+                na::Vector6::from(chain.solve(&delta).dofs())
+            })
+            .collect();
+
+        let mean = motions.iter().sum::<na::Vector6<f32>>() / samples as f32;
+        let covariance = motions.iter().map(|m| (m - mean) * (m - mean).transpose()).sum::<na::Matrix6<f32>>() / (samples - 1) as f32;
+        let dofs = std::array::from_fn(|dof| {
+            let mut values: Vec<f32> = motions.iter().map(|m| m[dof]).collect();
+            values.sort_by(f32::total_cmp);
+            let percentile = |p: f32| values[((p * (samples - 1) as f32).round() as usize).min(samples - 1)];
+            Interval {
+                mean: mean[dof],
+                std: covariance[(dof, dof)].sqrt(),
+                lower: percentile(0.025),
+                upper: percentile(0.975),
+            }
+        });
+        Ok(Propagation {
+            nominal: chain.solve(&[0.0; PARAMETERS]),
+            dofs,
+            covariance,
+        })
+    }
+
+    /// First-order intervals from the Jacobian of the motion with respect to every landmark and
+    /// tracker coordinate, by central differences.
+    pub fn linearised<S, const A: usize, const B: usize>(
+        &self,
+        solver: &S,
+        first: &RigidBody<A>,
+        second: &RigidBody<B>,
+        poses: (gT<Tracker<RigidBody<A>>>, gT<Tracker<RigidBody<B>>>),
+    ) -> Propagation
+    where
+        S: Solver<F = RigidBody<A>, T = RigidBody<B>>,
+        RigidBody<A>: DefinedTracker,
+        RigidBody<B>: DefinedTracker,
+    {
+        let chain = Chain { solver, first, second, poses };
+        let nominal = chain.solve(&[0.0; PARAMETERS]);
+        let mut covariance = na::Matrix6::zeros();
+        for (i, sigma) in self.sigmas().into_iter().enumerate() {
+            if sigma == 0.0 {
+                continue;
+            }
+            // Column of the Jacobian scaled by the standard deviation
+            let mut delta = [0.0; PARAMETERS];
+            delta[i] = STEP * sigma;
+            let plus = na::Vector6::from(chain.solve(&delta).dofs());
+            delta[i] = -STEP * sigma;
+            let minus = na::Vector6::from(chain.solve(&delta).dofs());
+            let column = (plus - minus) / (2.0 * STEP);
+            covariance += column * column.transpose();
+        }
+        let dofs = std::array::from_fn(|dof| {
+            let (mean, std) = (nominal.dofs()[dof], covariance[(dof, dof)].sqrt());
+            Interval {
+                mean,
+                std,
+                lower: mean - Z_95 * std,
+                upper: mean + Z_95 * std,
+            }
+        });
+        Propagation { nominal, dofs, covariance }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bone_to_tracker::{Femur, Side, Tibia};
+    use crate::data::Datum;
+    use crate::solvers::GroodAndSuntay;
+    use crate::synthetic::SyntheticKnee;
+
+    type Poses = (gT<Tracker<Femur>>, gT<Tracker<Tibia>>);
+
+    fn knee(motion: &Motion) -> (Femur, Tibia, Poses) {
+        let knee = SyntheticKnee::new(Side::Left);
+        let (femur, tibia) = knee.rigid_bodies(&Motion::from_dofs([0.0; 6])).unwrap();
+        let frame = &knee.frames(std::slice::from_ref(motion)).unwrap()[0];
+        let femur_pose = Datum::from_frame(frame, "Y").unwrap().to_transform();
+        let tibia_pose = Datum::from_frame(frame, "T").unwrap().to_transform();
+        (femur, tibia, (femur_pose, tibia_pose))
+    }
+
+    #[test]
+    fn no_uncertainty_gives_point_intervals() {
+        let motion = Motion::from_dofs([30.0, 5.0, 2.0, 4.0, 1.0, 0.5]);
+        let (femur, tibia, poses) = knee(&motion);
+        let result = Uncertainty::default().linearised(&GroodAndSuntay::tibiofemoral(), &femur, &tibia, poses);
+        for ((interval, nominal), expected) in result.dofs.iter().zip(result.nominal.dofs()).zip(motion.dofs()) {
+            assert_relative_eq!(nominal, expected, epsilon = 1e-2);
+            assert_eq!((interval.lower, interval.upper), (nominal, nominal));
+        }
+    }
+
+    #[test]
+    fn linearised_matches_monte_carlo() {
+        let (femur, tibia, poses) = knee(&Motion::from_dofs([30.0, 5.0, 2.0, 4.0, 1.0, 0.5]));
+        let uncertainty = Uncertainty {
+            landmark: 1.0,
+            tracker_translation: 0.2,
+            tracker_rotation: 0.1,
+        };
+        let solver = GroodAndSuntay::tibiofemoral();
+        let linear = uncertainty.linearised(&solver, &femur, &tibia, poses);
+        let sampled = uncertainty.monte_carlo(&solver, &femur, &tibia, poses, 2000, 1).unwrap();
+        for (l, s) in linear.dofs.iter().zip(&sampled.dofs) {
+            assert!(l.std > 0.0);
+            assert_relative_eq!(l.std, s.std, max_relative = 0.15);
+            assert!(s.lower < l.mean && l.mean < s.upper);
+        }
+    }
+
+    #[test]
+    fn monte_carlo_needs_two_samples() {
+        let (femur, tibia, poses) = knee(&Motion::from_dofs([30.0, 5.0, 2.0, 4.0, 1.0, 0.5]));
+        let result = Uncertainty::default().monte_carlo(&GroodAndSuntay::tibiofemoral(), &femur, &tibia, poses, 1, 1);
+        assert!(matches!(result, Err(Error::TooFewSamples(1))));
+    }
+}
@@ -0,0 +1,264 @@
+//! Processing every trial of a session in parallel.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+
+use crate::bone_to_tracker::Motion;
+use crate::data::QualityThresholds;
+use crate::session::{Session, Trial};
+use crate::stream::write_csv;
+use crate::{Error, Result};
+
+/// Name of the per-trial status file written next to the results.
+pub const SUMMARY: &str = "summary.csv";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrialOutcome {
+    pub name: String,
+    pub output: PathBuf,
+    pub frames: usize,
+    /// Frames without a motion.
+    pub gaps: usize,
+}
+
+#[derive(Debug)]
+pub struct TrialFailure {
+    pub name: String,
+    pub error: Error,
+}
+
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    /// In the order of the session's trials, as are the failures.
+    pub succeeded: Vec<TrialOutcome>,
+    pub failed: Vec<TrialFailure>,
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.succeeded.len() + self.failed.len();
+        writeln!(f, "{} of {total} trials processed", self.succeeded.len())?;
+        for failure in &self.failed {
+            writeln!(f, "  {}: {}", failure.name, failure.error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Tibiofemoral kinematics of every trial in a session, one CSV per trial.
+#[derive(Debug)]
+pub struct Batch<'a> {
+    session: &'a Session,
+    thresholds: QualityThresholds,
+    threads: Option<usize>,
+}
+
+impl<'a> Batch<'a> {
+    pub fn new(session: &'a Session) -> Self {
+        Self {
+            session,
+            thresholds: QualityThresholds::default(),
+            threads: None,
+        }
+    }
+    pub fn with_thresholds(mut self, thresholds: QualityThresholds) -> Self {
+        self.thresholds = thresholds;
+        self
+    }
+    /// Number of worker threads; defaults to one per core.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    // Written next to the output and renamed once complete, so a failing trial leaves no file
+    fn process(&self, trial: &Trial, dir: &Path) -> Result<TrialOutcome> {
+        let output = dir.join(format!("{}.csv", trial.name));
+        let partial = dir.join(format!("{}.csv.part", trial.name));
+        let written = self.write(trial, &partial).and_then(|counts| {
+            std::fs::rename(&partial, &output)?;
+            Ok(counts)
+        });
+        match written {
+            Ok((frames, gaps)) => Ok(TrialOutcome {
+                name: trial.name.clone(),
+                output,
+                frames,
+                gaps,
+            }),
+            Err(e) => {
+                let _ = std::fs::remove_file(&partial);
+                Err(e)
+            }
+        }
+    }
+
+    // Frames and gaps written to `path`
+    fn write(&self, trial: &Trial, path: &Path) -> Result<(usize, usize)> {
+        let mut gaps = 0;
+        let count_gaps = |m: &Result<Option<Motion>>| {
+            if matches!(m, Ok(None)) {
+                gaps += 1;
+            }
+        };
+        let mut writer = BufWriter::new(File::create(path)?);
+        // Filtering needs the whole trial in memory; otherwise stream it
+        let frames = if self.session.filter.is_some() {
+            let kinematics = self.session.kinematics(trial, &self.thresholds)?;
+            write_csv(&mut writer, kinematics.frames().iter().map(|m| Ok(*m)).inspect(count_gaps))?
+        } else {
+            write_csv(&mut writer, self.session.stream(trial, &self.thresholds)?.inspect(count_gaps))?
+        };
+        writer.flush()?;
+        Ok((frames, gaps))
+    }
+
+    /// Writes `<trial name>.csv` for every trial and a [`SUMMARY`] into `dir`. A failing trial
+    /// is reported and does not stop the others. Fails before writing anything if a trial name
+    /// is not a distinct, plain file name; otherwise only errors writing the summary are returned.
+    pub fn run<P: AsRef<Path>>(&self, dir: P) -> Result<BatchReport> {
+        let dir = dir.as_ref();
+        check_names(&self.session.trials)?;
+        std::fs::create_dir_all(dir)?;
+        let process = || -> Vec<_> { self.session.trials.par_iter().map(|t| (t, self.process(t, dir))).collect() };
+        let results = match self.threads {
+            Some(threads) => rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .map_err(|e| Error::ThreadPool(e.to_string()))?
+                .install(process),
+            None => process(),
+        };
+
+        let mut report = BatchReport::default();
+        let mut summary = BufWriter::new(File::create(dir.join(SUMMARY))?);
+        writeln!(summary, "trial,status,frames,gaps,error")?;
+        for (trial, result) in results {
+            match result {
+                Ok(outcome) => {
+                    writeln!(summary, "{},ok,{},{},", outcome.name, outcome.frames, outcome.gaps)?;
+                    report.succeeded.push(outcome);
+                }
+                Err(error) => {
+                    // Keep the message in one CSV field
+                    let message = error.to_string().replace(['"', '\n'], " ");
+                    writeln!(summary, "{},failed,,,\"{message}\"", trial.name)?;
+                    report.failed.push(TrialFailure {
+                        name: trial.name.clone(),
+                        error,
+                    });
+                }
+            }
+        }
+        summary.flush()?;
+        Ok(report)
+    }
+}
+
+// Names become file names in one directory, which may be case-insensitive
+fn check_names(trials: &[Trial]) -> Result<()> {
+    let summary = SUMMARY.trim_end_matches(".csv");
+    let mut seen = std::collections::HashSet::new();
+    for trial in trials {
+        let name = &trial.name;
+        let reason = if name.is_empty() || name == "." || name == ".." {
+            Some("is not a file name")
+        } else if name.contains(['/', '\\']) || name.chars().any(char::is_control) {
+            Some("contains a path separator or control character")
+        } else if name.eq_ignore_ascii_case(summary) {
+            Some("is reserved for the summary")
+        } else if !seen.insert(name.to_lowercase()) {
+            Some("is used by more than one trial")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            return Err(Error::InvalidTrialName { name: name.clone(), reason });
+        }
+    }
+    Ok(())
+}
+
+impl Session {
+    /// Adds every `.csv` file in `dir` as a trial named after the file, in name order, and
+    /// returns how many were added.
+    pub fn add_directory<P: AsRef<Path>>(&mut self, dir: P) -> Result<usize> {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<io::Result<_>>()?;
+        paths.retain(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case("csv")));
+        paths.sort();
+        for path in &paths {
+            let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_string();
+            self.add_trial(&name, path.clone(), false);
+        }
+        Ok(paths.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bone_to_tracker::{Motion, Side};
+    use crate::session::Tools;
+    use crate::synthetic::SyntheticKnee;
+
+    #[test]
+    fn processes_trials_and_reports_failures() {
+        let dir = std::env::temp_dir().join(format!("jcs-batch-{}", std::process::id()));
+        let recordings = dir.join("recordings");
+        std::fs::create_dir_all(&recordings).unwrap();
+        let knee = SyntheticKnee::new(Side::Right).with_dropout(0.2).with_seed(2);
+        for (name, frames) in [("a", 10), ("b", 25), ("c", 5)] {
+            let motions: Vec<_> = (0..frames).map(|i| Motion::from_dofs([i as f32, 0.0, 0.0, 0.0, 0.0, 0.0])).collect();
+            knee.write(recordings.join(format!("{name}.csv")).to_str().unwrap(), &motions).unwrap();
+        }
+        std::fs::write(recordings.join("broken.csv"), "Tools,Port 0x01: Y s/n:1,Frame\n").unwrap();
+        std::fs::write(recordings.join("notes.txt"), "not a trial").unwrap();
+
+        let (femur, tibia) = knee.rigid_bodies(&Motion::from_dofs([0.0; 6])).unwrap();
+        let mut session = Session::new("S01", Tools::new("38220010", "3B21FC02").unwrap(), &femur, &tibia);
+        assert_eq!(session.add_directory(&recordings).unwrap(), 4);
+
+        let output = dir.join("results");
+        let report = Batch::new(&session).with_threads(2).run(&output).unwrap();
+        let processed: Vec<_> = report.succeeded.iter().map(|o| (o.name.as_str(), o.frames)).collect();
+        assert_eq!(processed, [("a", 10), ("b", 25), ("c", 5)]);
+        assert!(report.succeeded.iter().any(|o| o.gaps > 0));
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].name, "broken");
+        assert!(matches!(report.failed[0].error, Error::Input(input::Error::MissingColumn { .. })));
+
+        let b = std::fs::read_to_string(output.join("b.csv")).unwrap();
+        assert_eq!(b.lines().count(), 26);
+        let summary = std::fs::read_to_string(output.join(SUMMARY)).unwrap();
+        assert_eq!(summary.lines().count(), 5);
+        assert!(summary.contains("broken,failed"));
+        assert!(report.to_string().starts_with("3 of 4 trials processed"));
+        // Nothing is left behind by the failed trial
+        assert!(!output.join("broken.csv").exists());
+        assert!(!output.join("broken.csv.part").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_unsafe_trial_names() {
+        let knee = SyntheticKnee::new(Side::Right);
+        let (femur, tibia) = knee.rigid_bodies(&Motion::from_dofs([0.0; 6])).unwrap();
+        let session = Session::new("S01", Tools::new("38220010", "3B21FC02").unwrap(), &femur, &tibia);
+        let output = std::env::temp_dir().join(format!("jcs-batch-names-{}", std::process::id()));
+        for names in [&["../escape"][..], &["Summary"], &["a", "b", "A"], &[""], &["a\\b"]] {
+            let mut session = session.clone();
+            for name in names {
+                session.add_trial(name, "trial.csv", false);
+            }
+            let error = Batch::new(&session).run(&output).unwrap_err();
+            assert!(matches!(error, Error::InvalidTrialName { .. }), "{names:?}: {error}");
+        }
+        assert!(!output.exists());
+    }
+}
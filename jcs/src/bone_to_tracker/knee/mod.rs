@@ -2,12 +2,15 @@
 mod rigid_body;
 mod solve;
 
-use crate::RigidBody;
+use crate::{Model, RigidBody};
 use crate::{transform::Transform, Tracker};
 use crate::data::Datum;
 pub type Tibia = RigidBody<1>;
 pub type Femur = RigidBody<2>;
 pub type Patella = RigidBody<3>;
+pub type TibiaModel = Model<Tibia>;
+pub type FemurModel = Model<Femur>;
+pub type PatellaModel = Model<Patella>;
 
 // use super::Global;
 
@@ -1,20 +1,30 @@
 use super::{Femur, Patella, Tibia};
-use crate::data::{Datum, ProbeData};
-use crate::transform::{gT, tT, Mldivide, Transform};
-use crate::{ProximalDistal, RigidBody, Tracker};
+use crate::data::ProbeData;
+use crate::transform::{gT, IsFrameOfReference, Transform};
+use crate::{Error, ProximalDistal, Result, RigidBody, Tracker};
 
 use nalgebra as na;
 
+// Landmarks closer than this (mm), or this close to a line, cannot define an anatomical frame
+const MIN_SEPARATION: f32 = 1e-3;
+
 use crate::bone_to_tracker::{orientation::*, DefinedTracker, Global, Landmark, Side};
 
 impl<const ID: usize> RigidBody<ID> {
+    /// Fails when the epicondyles coincide or the three landmarks are collinear.
     pub fn new(
         side: Side,
         medial: ProbeData,
         lateral: ProbeData,
         proximal_distal: ProbeData,
         tracker: ProbeData,
-    ) -> RigidBody<ID> {
+    ) -> Result<RigidBody<ID>> {
+        let (med, lat, pd) = (medial.translation(), lateral.translation(), proximal_distal.translation());
+        let across = lat - med;
+        let along = pd - (med + lat) / 2.0;
+        if across.norm() < MIN_SEPARATION || across.normalize().cross(&along).norm() < MIN_SEPARATION {
+            return Err(Error::DegenerateLandmarks(Self::name()));
+        }
         let name = "Black Probe";
         let label = "Probe";
         let med = Landmark::<RigidBody<ID>, Medial>::new(name, label, medial);
@@ -22,30 +32,19 @@ impl<const ID: usize> RigidBody<ID> {
         let prox_dist = Landmark::<RigidBody<ID>, ProximalDistal>::new(name, label, proximal_distal);
         let track = Transform::<Global, Tracker<RigidBody<ID>>>::new(tracker.to_transform());
 
-        Self {
+        Ok(Self {
             side,
             medial: med,
             lateral: lat,
             proximal_distal: prox_dist,
             tracker: track,
-        }
+            model: None,
+        })
     }
     pub fn tracker_in_global(probe_data: ProbeData) -> gT<Tracker<Self>> {
         Transform::<Global, Tracker<RigidBody<ID>>>::new(probe_data.to_transform())
     }
 }
-impl<const ID: usize> RigidBody<ID>
-where
-    Self: DefinedTracker,
-{
-    pub fn take_datum(&self, datum: Datum<Tracker<Self>>) -> gT<RigidBody<ID>> {
-        datum.to_transform() * self.in_tracker()
-    }
-    pub fn in_tracker(&self) -> tT<Self> {
-        self.tracker.mldivide(&self.in_global())
-    }
-}
-
 impl DefinedTracker for Tibia {
     fn in_global(&self) -> gT<Self> {
         let med = self.medial.translations();
@@ -54,10 +53,7 @@ impl DefinedTracker for Tibia {
         let origin = (med + lat) / 2.0;
 
         let tempk_ = na::Unit::new_normalize(origin - dist);
-        let i_ = match self.side {
-            Side::Right => na::Unit::new_normalize(lat - med),
-            Side::Left => na::Unit::new_normalize(med - lat),
-        };
+        let i_ = na::Unit::new_normalize((lat - med) * self.side.sign());
         let data = transform_from(origin, tempk_, i_);
         let data = na::Transform3::from_matrix_unchecked(data);
         Transform::<Global, Self>::new(data)
@@ -73,11 +69,7 @@ impl DefinedTracker for Femur {
         let origin = (med + lat) / 2.0;
 
         let tempk_ = na::Unit::new_normalize(prox - origin);
-        let i_ = match self.side {
-            // These are inverted from expectation
-            Side::Right => na::Unit::new_normalize(lat - med),
-            Side::Left => na::Unit::new_normalize(med - lat),
-        };
+        let i_ = na::Unit::new_normalize((lat - med) * self.side.sign());
         let data = transform_from(origin, tempk_, i_);
         let data = na::Transform3::from_matrix_unchecked(data);
         Transform::<Global, Self>::new(data)
@@ -92,10 +84,7 @@ impl DefinedTracker for Patella {
         let origin = (med + lat) / 2.0;
 
         let tempk_ = na::Unit::new_normalize(origin - dist);
-        let i_ = match self.side {
-            Side::Right => na::Unit::new_normalize(lat - med),
-            Side::Left => na::Unit::new_normalize(med - lat),
-        };
+        let i_ = na::Unit::new_normalize((lat - med) * self.side.sign());
         let data = transform_from(origin, tempk_, i_);
         let data = na::Transform3::from_matrix_unchecked(data);
         Transform::<Global, Self>::new(data)
@@ -162,8 +151,19 @@ mod test {
         );
         let tracker = femur_probe_data.into();
 
-        let femur = Femur::new(side, fm, fl, fp, tracker);
+        let femur = Femur::new(side, fm, fl, fp, tracker).unwrap();
         println!("Femur in global {}", femur.in_global());
         println!("Femur in tracker {}", femur.in_tracker());
     }
+
+    #[test]
+    fn rejects_degenerate_landmarks() {
+        let point = |x: f32, y: f32, z: f32| -> ProbeData { ProbeRawData::new(NAME, LABEL, 1.0, 0.0, 0.0, 0.0, x, y, z).into() };
+        let tracker = || point(0.0, 0.0, 0.0);
+        let coincident = Femur::new(Side::Right, point(1.0, 2.0, 3.0), point(1.0, 2.0, 3.0), point(0.0, 0.0, 50.0), tracker());
+        assert!(matches!(coincident, Err(Error::DegenerateLandmarks(bone)) if bone == "RigidBody<2>"));
+        let collinear = Tibia::new(Side::Left, point(-40.0, 0.0, 0.0), point(40.0, 0.0, 0.0), point(80.0, 0.0, 0.0), tracker());
+        assert!(matches!(collinear, Err(Error::DegenerateLandmarks(_))));
+        assert!(Tibia::new(Side::Left, point(-40.0, 0.0, 0.0), point(40.0, 0.0, 0.0), point(0.0, 0.0, -80.0), tracker()).is_ok());
+    }
 }
@@ -4,9 +4,12 @@ use crate::{
     bone_to_tracker::{Global, Motion, Side},
     solvers::{GroodAndSuntay, Solver},
     transform::{gT, IsFrameOfReference, Transform},
+    Error, Result,
 };
 
-use super::{Femur, Patella, Tibia};
+use nalgebra as na;
+
+use super::{Femur, Tibia};
 
 impl GroodAndSuntay {
     pub fn tibiofemoral() -> GroodAndSuntayKnee<Femur, Tibia> {
@@ -15,12 +18,6 @@ impl GroodAndSuntay {
             v: PhantomData,
         }
     }
-    pub fn patellofemoral() -> GroodAndSuntayKnee<Femur, Patella> {
-        GroodAndSuntayKnee {
-            t: PhantomData,
-            v: PhantomData,
-        }
-    }
 }
 
 pub struct GroodAndSuntayKnee<A: IsFrameOfReference, B: IsFrameOfReference> {
@@ -28,6 +25,30 @@ pub struct GroodAndSuntayKnee<A: IsFrameOfReference, B: IsFrameOfReference> {
     v: PhantomData<B>,
 }
 
+impl GroodAndSuntayKnee<Femur, Tibia> {
+    /// Inverse of [`Solver::solve`]: the tibial pose that gives `motion` with the femur at `femur`.
+    /// Fails when the tibia ends up perpendicular to the flexion axis.
+    pub fn tibia_pose(&self, femur: gT<Femur>, motion: &Motion, side: Side) -> Result<gT<Tibia>> {
+        // Flexion about the femoral i axis, varus about the floating axis, external rotation
+        // about the tibial k axis
+        let s = side.sign();
+        let flexion = na::UnitQuaternion::from_axis_angle(&na::Vector3::x_axis(), -motion.flexion.to_radians());
+        let varus = na::UnitQuaternion::from_axis_angle(&na::Vector3::y_axis(), s * motion.varus.to_radians());
+        let external = na::UnitQuaternion::from_axis_angle(&na::Vector3::z_axis(), -s * motion.external.to_radians());
+        let rotation = flexion * varus * external;
+
+        // Translations are components along the non-orthogonal i, e2, k axes
+        let e2 = flexion * na::Vector3::y();
+        let k = rotation * na::Vector3::z();
+        let axes = na::Matrix3::from_rows(&[na::Vector3::x().transpose(), e2.transpose(), k.transpose()]);
+        let components = na::Vector3::new(s * motion.lateral, motion.anterior, -motion.distal);
+        let h = axes.try_inverse().ok_or(Error::SingularTransform)? * components;
+
+        let origin = femur.origin() + femur.rotation() * h;
+        Ok(Transform::from_parts(&origin.coords, &(femur.rotation() * rotation)))
+    }
+}
+
 impl Solver for GroodAndSuntayKnee<Femur, Tibia> {
     type F = Femur;
     type T = Tibia;
@@ -40,19 +61,10 @@ impl Solver for GroodAndSuntayKnee<Femur, Tibia> {
         let flexion = (-e2).dot(&femur.k()).asin().to_degrees();
         let beta = femur.i().dot(&tibia.k()).acos().to_degrees(); // epicondylar projected onto tibial superior-inferior
 
-        let external = match side {
-            Side::Right => (-e2).dot(&tibia.i()).asin().to_degrees(),
-            Side::Left => e2.dot(&tibia.i()).asin().to_degrees(),
-        };
-        let varus = match side {
-            Side::Right => 90.0 - beta,
-            Side::Left => 90.0 - beta,
-        };
+        let external = -side.sign() * e2.dot(&tibia.i()).asin().to_degrees();
+        let varus = side.sign() * (90.0 - beta);
         let h = tibia.origin() - femur.origin();
-        let lateral = match side {
-            Side::Right => h.dot(&femur.i()),
-            Side::Left => h.dot(&-femur.i()),
-        };
+        let lateral = side.sign() * h.dot(&femur.i());
         let anterior = h.dot(&e2);
         let distal = -h.dot(&tibia.k());
 
@@ -67,15 +79,6 @@ impl Solver for GroodAndSuntayKnee<Femur, Tibia> {
     }
 }
 
-impl Solver for GroodAndSuntayKnee<Femur, Patella> {
-    type F = Femur;
-    type T = Tibia;
-
-    fn solve(&self, rb1: gT<Self::F>, rb2: gT<Self::T>, side: Side) -> Motion {
-        todo!()
-    }
-}
-
 #[cfg(test)]
 mod test {
     use super::Solver;
@@ -105,5 +108,62 @@ mod test {
             Transform::<Global, Tibia>::new(b)
         };
         let motion = GroodAndSuntay::tibiofemoral().solve(g_t_f, g_t_t, side);
+        // Worked by hand from the matrices: a Cardan x-y'-z'' decomposition of the tibia in the
+        // femur frame, with the translation projected onto i, e2 and k
+        let expected = Motion::from_dofs([-27.7482, -5.3788, 3.5646, 7.5744, 35.2313, 0.4502]);
+        for (name, (a, b)) in Motion::DOF_NAMES.iter().zip(motion.dofs().into_iter().zip(expected.dofs())) {
+            assert!((a - b).abs() < 1e-2, "{name}: {a} != {b}");
+        }
+    }
+
+    #[test]
+    fn inverts_the_solver() {
+        let solver = GroodAndSuntay::tibiofemoral();
+        let femur = Transform::<Global, Femur>::from_parts(
+            &na::Vector3::new(-40.0, 20.0, -2000.0),
+            &na::UnitQuaternion::from_euler_angles(0.4, -0.3, 1.2),
+        );
+        let motion = Motion::from_dofs([65.0, -12.0, 4.0, 8.0, -3.0, 2.5]);
+        for side in [Side::Right, Side::Left] {
+            let tibia = solver.tibia_pose(femur, &motion, side).unwrap();
+            let solved = solver.solve(femur, tibia, side);
+            for (a, b) in solved.dofs().into_iter().zip(motion.dofs()) {
+                assert_relative_eq!(a, b, epsilon = 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn kinematics_have_gaps_for_invalid_frames() {
+        use crate::bone_to_tracker::Kinematics;
+        use crate::data::{Datum, ProbeRawData, QualityThresholds};
+
+        let (name, label) = ("Black Probe", "Probe");
+        let femur = Femur::new(
+            Side::Left,
+            ProbeRawData::new(name, label, 0.8228, 0.1357, 0.4408, -0.3318, 15.3196, -54.9971, -2097.6023).into(),
+            ProbeRawData::new(name, label, 0.4031, 0.4746, 0.4195, -0.6603, 16.9156, 16.2064, -2059.3142).into(),
+            ProbeRawData::new(name, label, 0.4280, 0.4662, 0.4471, -0.6319, -8.5689, 15.8874, -2131.4353).into(),
+            ProbeRawData::new(name, label, 0.9573733, -0.0372205, -0.1895465, 0.2147628, -149.371, -19.411, -2148.287).into(),
+        ).unwrap();
+        let tibia = Tibia::new(
+            Side::Left,
+            ProbeRawData::new(name, label, 0.8156, 0.0787, 0.4628, -0.3381, 66.899, -61.4777, -2078.4102).into(),
+            ProbeRawData::new(name, label, 0.4197, 0.4198, 0.3991, -0.6987, 65.8513, -6.3346, -2031.8842).into(),
+            ProbeRawData::new(name, label, 0.4268, 0.2327, 0.5690, -0.6632, 209.2022, -37.8499, -2040.4506).into(),
+            ProbeRawData::new(name, label, 0.0230, -0.1878, 0.0213, 0.9817, 128.0411, 196.8627, -2024.9063).into(),
+        ).unwrap();
+        let mut frames = input::polaris::read(concat!(env!("CARGO_MANIFEST_DIR"), "/../input/data.csv")).unwrap();
+        frames[1].tools[0].error = Some(5.0);
+        frames[2].tools[1].state = input::State::Missing;
+        frames[3].tools[1].rotation = None;
+
+        let data = frames.iter().map(|f| (Datum::from_frame(f, "Y"), Datum::from_frame(f, "T")));
+        let kinematics = Kinematics::from_data(&GroodAndSuntay::tibiofemoral(), &femur, &tibia, data, &QualityThresholds::default());
+
+        assert_eq!(kinematics.len(), frames.len());
+        assert_eq!(kinematics.gaps(), 3);
+        assert!(kinematics.frames()[0].is_some());
+        assert!(kinematics.frames()[1..4].iter().all(Option::is_none));
     }
 }
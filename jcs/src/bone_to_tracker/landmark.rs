@@ -3,7 +3,10 @@ use super::orientation::*;
 
 use crate::IsFrameOfReference;
 use crate::data::{ProbeData, ProbeRawData};
+use crate::transform::check_frame;
 use nalgebra as na;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 #[derive(Debug)]
 pub struct Landmark<RB: IsFrameOfReference, O: Orientation> {
@@ -13,7 +16,7 @@ pub struct Landmark<RB: IsFrameOfReference, O: Orientation> {
     orientation: PhantomData<O>,
 }
 
-impl<'a, RB: IsFrameOfReference, O: Orientation> std::fmt::Display for Landmark<RB, O> {
+impl<RB: IsFrameOfReference, O: Orientation> std::fmt::Display for Landmark<RB, O> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}\nbone: {:?}\norientation: {:?}\n", self.position, self.bone, self.orientation)
     }
@@ -26,8 +29,61 @@ impl<RB: IsFrameOfReference, O: Orientation> Landmark<RB, O> {
     pub fn rotations(&self) -> &na::Quaternion<f32> {
         self.position.rotation()
     }
+    pub(crate) fn probe_data(&self) -> &ProbeData {
+        &self.position
+    }
     // #[cfg(test)]
     pub(crate) fn new(probe_name: &str, probe_label: &str, position: ProbeData) -> Self {
         Self { position, bone: PhantomData, orientation: PhantomData }
     }
 }
+
+#[derive(Serialize, Deserialize)]
+struct Tagged {
+    bone: String,
+    orientation: String,
+    position: ProbeData,
+}
+
+impl<RB: IsFrameOfReference, O: Orientation> Serialize for Landmark<RB, O> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Tagged {
+            bone: RB::name(),
+            orientation: O::name().to_string(),
+            position: self.position.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, RB: IsFrameOfReference, O: Orientation> Deserialize<'de> for Landmark<RB, O> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let tagged = Tagged::deserialize(deserializer)?;
+        check_frame::<RB, _>(&tagged.bone)?;
+        if tagged.orientation != O::name() {
+            return Err(D::Error::custom(format!("orientation mismatch: expected {}, found {}", O::name(), tagged.orientation)));
+        }
+        Ok(Self::new("", "", tagged.position))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bone_to_tracker::{Femur, Motion, Tibia};
+
+    #[test]
+    fn serializes_landmarks_and_motion() {
+        let position: ProbeData = ProbeRawData::new("Black Probe", "Probe", 0.8228, 0.1357, 0.4408, -0.3318, 15.3196, -54.9971, -2097.6023).into();
+        let medial = Landmark::<Femur, Medial>::new("Black Probe", "Probe", position.clone());
+        let text = toml::to_string(&medial).unwrap();
+        let loaded: Landmark<Femur, Medial> = toml::from_str(&text).unwrap();
+        assert_eq!(loaded.probe_data(), &position);
+        assert!(toml::from_str::<Landmark<Femur, Lateral>>(&text).unwrap_err().to_string().contains("orientation mismatch"));
+        assert!(toml::from_str::<Landmark<Tibia, Medial>>(&text).unwrap_err().to_string().contains("frame mismatch"));
+
+        let motion = Motion::from_dofs([61.5, -12.0, 0.8, 22.1, 52.7, -0.8]);
+        let loaded: Motion = toml::from_str(&toml::to_string(&motion).unwrap()).unwrap();
+        assert_eq!(loaded, motion);
+    }
+}
@@ -0,0 +1,158 @@
+use nalgebra as na;
+
+use super::orientation::Orientation;
+use super::Landmark;
+use crate::data::{Datum, ProbeData};
+use crate::mesh::Mesh;
+use crate::series::PoseSeries;
+use crate::transform::{IsFrameOfReference, Transform};
+use crate::{Marker, RigidBody};
+
+/// Reflection through the global YZ plane, converting right-side data into left-side
+/// convention and back.
+///
+/// Every frame is reflected in the same way, so a pose `T` becomes `M T M` with
+/// `M = diag(-1, 1, 1)`. Points become `M p`; a frame built from mirrored landmarks with the
+/// opposite `Side` is exactly the mirrored frame, and solvers return the same `Motion` for both.
+pub trait Mirror {
+    fn mirrored(&self) -> Self;
+}
+
+fn reflect(v: &na::Vector3<f32>) -> na::Vector3<f32> {
+    na::Vector3::new(-v.x, v.y, v.z)
+}
+
+// M R M for M = diag(-1, 1, 1)
+fn reflect_rotation(q: &na::UnitQuaternion<f32>) -> na::UnitQuaternion<f32> {
+    na::UnitQuaternion::new_unchecked(na::Quaternion::new(q.w, q.i, -q.j, -q.k))
+}
+
+impl<A: IsFrameOfReference, B: IsFrameOfReference> Mirror for Transform<A, B> {
+    fn mirrored(&self) -> Self {
+        Transform::from_parts(&reflect(&self.translation().coords), &reflect_rotation(&self.rotation()))
+    }
+}
+
+impl Mirror for ProbeData {
+    fn mirrored(&self) -> Self {
+        self.with_pose(reflect(self.translation()), reflect_rotation(self.rotation()))
+    }
+}
+
+impl<M: Marker + IsFrameOfReference> Mirror for Datum<M> {
+    fn mirrored(&self) -> Self {
+        Datum::with_quality(self.data().mirrored(), *self.quality())
+    }
+}
+
+impl<RB: IsFrameOfReference, O: Orientation> Mirror for Landmark<RB, O> {
+    fn mirrored(&self) -> Self {
+        Landmark::new("", "", self.probe_data().mirrored())
+    }
+}
+
+impl<F: IsFrameOfReference> Mirror for PoseSeries<F> {
+    fn mirrored(&self) -> Self {
+        self.map(Mirror::mirrored)
+    }
+}
+
+impl<F: IsFrameOfReference> Mirror for Mesh<F> {
+    /// Reflected surface; faces are rewound so that normals still point outwards.
+    fn mirrored(&self) -> Self {
+        let vertices = self.vertices().iter().map(|v| na::Point3::from(reflect(&v.coords))).collect();
+        let faces = self.faces().iter().map(|[a, b, c]| [*a, *c, *b]).collect();
+        let normals = self.normals().iter().map(reflect).collect();
+        Mesh::from_parts(vertices, faces, normals)
+    }
+}
+
+impl<const ID: usize> Mirror for RigidBody<ID> {
+    /// The contralateral bone: landmarks, tracker and model reflected and the side swapped.
+    fn mirrored(&self) -> Self {
+        Self {
+            side: self.side.opposite(),
+            medial: self.medial.mirrored(),
+            lateral: self.lateral.mirrored(),
+            proximal_distal: self.proximal_distal.mirrored(),
+            tracker: self.tracker.mirrored(),
+            model: self.model.as_ref().map(Mirror::mirrored),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bone_to_tracker::{DefinedTracker, Femur, Side, Tibia};
+    use crate::data::ProbeRawData;
+    use crate::mesh::ellipsoid;
+    use crate::solvers::{GroodAndSuntay, Solver};
+    use crate::Model;
+
+    const NAME: &str = "Black Probe";
+    const LABEL: &str = "Probe";
+
+    fn knee(side: Side) -> (Femur, Tibia) {
+        let femur = Femur::new(
+            side,
+            ProbeRawData::new(NAME, LABEL, 0.8228, 0.1357, 0.4408, -0.3318, 15.3196, -54.9971, -2097.6023).into(),
+            ProbeRawData::new(NAME, LABEL, 0.4031, 0.4746, 0.4195, -0.6603, 16.9156, 16.2064, -2059.3142).into(),
+            ProbeRawData::new(NAME, LABEL, 0.4280, 0.4662, 0.4471, -0.6319, -8.5689, 15.8874, -2131.4353).into(),
+            ProbeRawData::new(NAME, LABEL, 0.9573733, -0.0372205, -0.1895465, 0.2147628, -149.371, -19.411, -2148.287).into(),
+        ).unwrap();
+        let tibia = Tibia::new(
+            side,
+            ProbeRawData::new(NAME, LABEL, 0.8156, 0.0787, 0.4628, -0.3381, 66.899, -61.4777, -2078.4102).into(),
+            ProbeRawData::new(NAME, LABEL, 0.4197, 0.4198, 0.3991, -0.6987, 65.8513, -6.3346, -2031.8842).into(),
+            ProbeRawData::new(NAME, LABEL, 0.4268, 0.2327, 0.5690, -0.6632, 209.2022, -37.8499, -2040.4506).into(),
+            ProbeRawData::new(NAME, LABEL, 0.0230, -0.1878, 0.0213, 0.9817, 128.0411, 196.8627, -2024.9063).into(),
+        ).unwrap();
+        (femur, tibia)
+    }
+
+    #[test]
+    fn mirroring_is_an_involution() {
+        let pose = Transform::<Femur, Model<Femur>>::from_parts(
+            &na::Vector3::new(1.0, 2.0, 3.0),
+            &na::UnitQuaternion::from_euler_angles(0.3, -0.2, 0.9),
+        );
+        assert_relative_eq!(pose.mirrored().mirrored().inner(), pose.inner(), epsilon = 1e-6);
+        let m = na::Matrix4::from_diagonal(&na::Vector4::new(-1.0, 1.0, 1.0, 1.0));
+        assert_relative_eq!(pose.mirrored().inner().matrix(), &(m * pose.inner().matrix() * m), epsilon = 1e-6);
+
+        let mesh = ellipsoid::<Model<Femur>>(10.0, 5.0, 5.0, 8);
+        let mirrored = mesh.mirrored();
+        let [a, b, c] = mirrored.triangle(0);
+        assert!((b - a).cross(&(c - a)).dot(&mirrored.normals()[0]) > 0.0);
+    }
+
+    #[test]
+    fn mirrored_trial_gives_identical_motion() {
+        let (femur, tibia) = knee(Side::Left);
+        let (right_femur, right_tibia) = (femur.mirrored(), tibia.mirrored());
+        assert_eq!(right_femur.side, Side::Right);
+        // Frames built from mirrored landmarks are the mirrored frames
+        assert_relative_eq!(right_femur.in_global().inner(), femur.in_global().mirrored().inner(), epsilon = 1e-4);
+
+        let samples = [
+            (
+                ProbeRawData::new(NAME, LABEL, 0.7169, 0.1776, 0.4286, 0.5204, -37.7920, -136.356, -1953.203),
+                ProbeRawData::new(NAME, LABEL, 0.0347, -0.1902, 0.0599, 0.9793, 128.2, 205.321, -2050.397),
+            ),
+            (
+                ProbeRawData::new(NAME, LABEL, 0.9573733, -0.0372205, -0.1895465, 0.2147628, -149.371, -19.411, -2148.287),
+                ProbeRawData::new(NAME, LABEL, 0.0230, -0.1878, 0.0213, 0.9817, 128.0411, 196.8627, -2024.9063),
+            ),
+        ];
+        let solver = GroodAndSuntay::tibiofemoral();
+        for (f, t) in samples {
+            let left = solver.solve(femur.take_datum(f.into()), tibia.take_datum(t.into()), Side::Left);
+            let (f, t) = (Datum::from(f).mirrored(), Datum::from(t).mirrored());
+            let right = solver.solve(right_femur.take_datum(f), right_tibia.take_datum(t), Side::Right);
+            for (l, r) in left.dofs().into_iter().zip(right.dofs()) {
+                assert_relative_eq!(l, r, epsilon = 1e-2);
+            }
+        }
+    }
+}
@@ -3,29 +3,63 @@ pub mod hip;
 #[cfg(feature = "knee")]
 pub mod knee;
 mod landmark;
+mod mirror;
 mod orientation;
 #[cfg(feature = "shoulder")]
 pub mod shoulder;
+mod virtual_landmark;
 
 pub use landmark::Landmark;
+pub use mirror::Mirror;
+pub use virtual_landmark::VirtualLandmark;
 pub use orientation::*;
 
 #[cfg(feature = "knee")]
-pub use knee::{Femur, Patella, Tibia};
+pub use knee::{Femur, FemurModel, Patella, PatellaModel, Tibia, TibiaModel};
 
-use crate::transform::gT;
+use serde::{Deserialize, Serialize};
+
+use crate::data::{Datum, QualityThresholds};
+use crate::series::PoseSeries;
+use crate::solvers::Solver;
+use crate::transform::{gT, tT};
 use crate::transform::IsFrameOfReference;
+use crate::mesh::Mesh;
+use crate::{Model, RigidBody, Tracker};
 
 #[derive(Debug)]
 pub struct Global;
-impl IsFrameOfReference for Global {}
+impl IsFrameOfReference for Global {
+    fn name() -> String {
+        "Global".to_string()
+    }
+}
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Side {
     Right,
     Left,
 }
-#[derive(Debug)]
+
+impl Side {
+    pub fn opposite(&self) -> Side {
+        match self {
+            Side::Right => Side::Left,
+            Side::Left => Side::Right,
+        }
+    }
+    /// +1 for the right side, -1 for the left. Mediolateral axes are multiplied by this so that
+    /// they point to the subject's right on both sides, and a mirrored left side gives the same
+    /// `Motion` as the right.
+    pub fn sign(&self) -> f32 {
+        match self {
+            Side::Right => 1.0,
+            Side::Left => -1.0,
+        }
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
 pub struct Motion {
     flexion: f32,
     external: f32,
@@ -35,10 +69,151 @@ pub struct Motion {
     lateral: f32,
 }
 
-pub struct Kinematics(Vec<Motion>);
+impl Motion {
+    pub const DOF_NAMES: [&'static str; 6] = ["flexion", "external", "varus", "anterior", "distal", "lateral"];
+
+    /// Degrees of freedom in the order of [`Motion::DOF_NAMES`].
+    pub fn dofs(&self) -> [f32; 6] {
+        [self.flexion, self.external, self.varus, self.anterior, self.distal, self.lateral]
+    }
+    pub fn from_dofs(dofs: [f32; 6]) -> Self {
+        let [flexion, external, varus, anterior, distal, lateral] = dofs;
+        Self {
+            flexion,
+            external,
+            varus,
+            anterior,
+            distal,
+            lateral,
+        }
+    }
+    pub fn flexion(&self) -> f32 {
+        self.flexion
+    }
+    pub fn external(&self) -> f32 {
+        self.external
+    }
+    pub fn varus(&self) -> f32 {
+        self.varus
+    }
+    pub fn anterior(&self) -> f32 {
+        self.anterior
+    }
+    pub fn distal(&self) -> f32 {
+        self.distal
+    }
+    pub fn lateral(&self) -> f32 {
+        self.lateral
+    }
+}
+
+/// Motion per frame; `None` where either tracker was missing or failed the quality thresholds.
+#[derive(Debug, Default, Clone)]
+pub struct Kinematics(Vec<Option<Motion>>);
+
+impl Kinematics {
+    pub fn from_data<S, I, const A: usize, const B: usize>(
+        solver: &S,
+        first: &RigidBody<A>,
+        second: &RigidBody<B>,
+        data: I,
+        thresholds: &QualityThresholds,
+    ) -> Self
+    where
+        S: Solver<F = RigidBody<A>, T = RigidBody<B>>,
+        RigidBody<A>: DefinedTracker,
+        RigidBody<B>: DefinedTracker,
+        I: IntoIterator<Item = (Option<Datum<Tracker<RigidBody<A>>>>, Option<Datum<Tracker<RigidBody<B>>>>)>,
+    {
+        let motions = data
+            .into_iter()
+            .map(|pair| match pair {
+                (Some(a), Some(b)) if a.is_valid(thresholds) && b.is_valid(thresholds) => {
+                    Some(solver.solve(first.take_datum(a), second.take_datum(b), first.side))
+                }
+                _ => None,
+            })
+            .collect();
+        Self(motions)
+    }
+    /// Motion from tracker pose series, e.g. after gap filling.
+    pub fn from_poses<S, const A: usize, const B: usize>(
+        solver: &S,
+        first: &RigidBody<A>,
+        second: &RigidBody<B>,
+        first_poses: &PoseSeries<Tracker<RigidBody<A>>>,
+        second_poses: &PoseSeries<Tracker<RigidBody<B>>>,
+    ) -> Self
+    where
+        S: Solver<F = RigidBody<A>, T = RigidBody<B>>,
+        RigidBody<A>: DefinedTracker,
+        RigidBody<B>: DefinedTracker,
+    {
+        let motions = first_poses
+            .poses()
+            .iter()
+            .zip(second_poses.poses())
+            .map(|pair| match pair {
+                (Some(a), Some(b)) => Some(solver.solve(first.take_pose(*a), second.take_pose(*b), first.side)),
+                _ => None,
+            })
+            .collect();
+        Self(motions)
+    }
+    pub fn frames(&self) -> &[Option<Motion>] {
+        &self.0
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn gaps(&self) -> usize {
+        self.0.iter().filter(|m| m.is_none()).count()
+    }
+}
+
+impl FromIterator<Option<Motion>> for Kinematics {
+    fn from_iter<I: IntoIterator<Item = Option<Motion>>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
 pub trait DefinedTracker
 where
     Self: IsFrameOfReference + Sized,
 {
     fn in_global(&self) -> gT<Self>;
 }
+
+impl<const ID: usize> RigidBody<ID> {
+    /// Attaches patient-specific geometry in the bone model frame.
+    pub fn with_model(mut self, model: Mesh<Model<Self>>) -> Self {
+        self.model = Some(model);
+        self
+    }
+    pub fn model(&self) -> Option<&Mesh<Model<Self>>> {
+        self.model.as_ref()
+    }
+}
+
+impl<const ID: usize> RigidBody<ID>
+where
+    Self: DefinedTracker,
+{
+    pub fn take_datum(&self, datum: Datum<Tracker<Self>>) -> gT<RigidBody<ID>> {
+        self.take_pose(datum.to_transform())
+    }
+    pub fn take_pose(&self, tracker: gT<Tracker<Self>>) -> gT<RigidBody<ID>> {
+        tracker * self.in_tracker()
+    }
+    /// Anatomical frame in global for every tracker pose.
+    pub fn bone_poses(&self, trackers: &PoseSeries<Tracker<Self>>) -> PoseSeries<Self> {
+        let in_tracker = self.in_tracker();
+        trackers.map(|p| *p * in_tracker)
+    }
+    pub fn in_tracker(&self) -> tT<Self> {
+        self.tracker.rigid_inverse() * self.in_global()
+    }
+}
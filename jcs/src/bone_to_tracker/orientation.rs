@@ -1,4 +1,6 @@
-pub trait Orientation {}
+pub trait Orientation {
+    fn name() -> &'static str;
+}
 #[derive(Debug)]
 pub struct Medial {}
 impl std::fmt::Display for Medial {
@@ -15,8 +17,28 @@ pub struct Posterior {}
 #[derive(Debug)]
 pub struct ProximalDistal {}
 
-impl Orientation for Medial {}
-impl Orientation for Lateral {}
-impl Orientation for Anterior {}
-impl Orientation for Posterior {}
-impl Orientation for ProximalDistal {}
+impl Orientation for Medial {
+    fn name() -> &'static str {
+        "Medial"
+    }
+}
+impl Orientation for Lateral {
+    fn name() -> &'static str {
+        "Lateral"
+    }
+}
+impl Orientation for Anterior {
+    fn name() -> &'static str {
+        "Anterior"
+    }
+}
+impl Orientation for Posterior {
+    fn name() -> &'static str {
+        "Posterior"
+    }
+}
+impl Orientation for ProximalDistal {
+    fn name() -> &'static str {
+        "ProximalDistal"
+    }
+}
@@ -0,0 +1,99 @@
+use std::marker::PhantomData;
+
+use nalgebra as na;
+
+use super::orientation::Orientation;
+use super::Landmark;
+use crate::data::ProbeRawData;
+use crate::series::PoseSeries;
+use crate::transform::{gT, IsFrameOfReference, Mldivide, Transform};
+use crate::{Error, Model, Result};
+
+/// Landmark picked on the bone model rather than probed, e.g. the femoral head centre.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VirtualLandmark<RB: IsFrameOfReference> {
+    name: String,
+    /// Position in the model frame.
+    position: na::Point3<f32>,
+    bone: PhantomData<RB>,
+}
+
+impl<RB: IsFrameOfReference> VirtualLandmark<RB> {
+    pub fn new(name: &str, position: na::Point3<f32>) -> Self {
+        Self {
+            name: name.to_string(),
+            position,
+            bone: PhantomData,
+        }
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn position(&self) -> &na::Point3<f32> {
+        &self.position
+    }
+    /// Position in the anatomical frame given the model registration.
+    pub fn in_bone(&self, registration: &Transform<RB, Model<RB>>) -> na::Point3<f32> {
+        registration.transform_point(&self.position)
+    }
+    /// Equivalent of a probed landmark taken with the bone at `bone`.
+    pub fn to_landmark<O: Orientation>(&self, registration: &Transform<RB, Model<RB>>, bone: &gT<RB>) -> Landmark<RB, O> {
+        let p = bone.transform_point(&self.in_bone(registration));
+        let raw = ProbeRawData::new(&self.name, "Virtual", 1.0, 0.0, 0.0, 0.0, p.x, p.y, p.z);
+        Landmark::new(&self.name, "Virtual", raw.into())
+    }
+    /// Position in global for every frame of `poses`.
+    pub fn track(&self, registration: &Transform<RB, Model<RB>>, poses: &PoseSeries<RB>) -> Vec<Option<na::Point3<f32>>> {
+        let p = self.in_bone(registration);
+        poses.poses().iter().map(|pose| pose.map(|pose| pose.transform_point(&p))).collect()
+    }
+    /// Position in the frame of another bone, e.g. a tibial landmark in the femoral frame. Fails
+    /// if the series differ in length.
+    pub fn track_in<G: IsFrameOfReference>(
+        &self,
+        registration: &Transform<RB, Model<RB>>,
+        poses: &PoseSeries<RB>,
+        reference: &PoseSeries<G>,
+    ) -> Result<Vec<Option<na::Point3<f32>>>> {
+        if poses.len() != reference.len() {
+            return Err(Error::LengthMismatch { expected: poses.len(), found: reference.len() });
+        }
+        let p = self.in_bone(registration);
+        Ok(poses
+            .poses()
+            .iter()
+            .zip(reference.poses())
+            .map(|(pose, reference)| {
+                let relative: Transform<G, RB> = reference.as_ref()?.mldivide(pose.as_ref()?).ok()?;
+                Some(relative.transform_point(&p))
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bone_to_tracker::{Femur, Medial, Tibia};
+
+    fn translation<A: IsFrameOfReference, B: IsFrameOfReference>(x: f32, y: f32, z: f32) -> Transform<A, B> {
+        Transform::from_parts(&na::Vector3::new(x, y, z), &na::UnitQuaternion::identity())
+    }
+
+    #[test]
+    fn follows_the_registered_bone() {
+        let spine = VirtualLandmark::<Tibia>::new("medial spine", na::Point3::new(0.0, 5.0, 40.0));
+        let registration = translation(0.0, 0.0, -30.0);
+        let tibia = PoseSeries::new(vec![0.0, 1.0], vec![Some(translation(100.0, 0.0, 0.0)), None]).unwrap();
+        let femur = PoseSeries::<Femur>::new(vec![0.0, 1.0], vec![Some(translation(100.0, 0.0, 50.0)); 2]).unwrap();
+
+        assert_eq!(spine.in_bone(&registration), na::Point3::new(0.0, 5.0, 10.0));
+        let global = spine.track(&registration, &tibia);
+        assert_eq!(global, vec![Some(na::Point3::new(100.0, 5.0, 10.0)), None]);
+        let in_femur = spine.track_in(&registration, &tibia, &femur).unwrap();
+        assert_relative_eq!(in_femur[0].unwrap(), na::Point3::new(0.0, 5.0, -40.0));
+
+        let landmark: Landmark<Tibia, Medial> = spine.to_landmark(&registration, &translation(100.0, 0.0, 0.0));
+        assert_eq!(*landmark.translations(), na::Vector3::new(100.0, 5.0, 10.0));
+    }
+}
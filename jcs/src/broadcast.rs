@@ -0,0 +1,152 @@
+//! Motions sent over UDP as they are computed, for biofeedback displays and other acquisition
+//! systems that follow a live session.
+
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+
+use serde::Serialize;
+
+use crate::bone_to_tracker::Motion;
+use crate::Result;
+
+/// OSC address pattern of motion messages.
+pub const OSC_ADDRESS: &str = "/knee/motion";
+
+/// Layout of the datagrams, one per frame with a motion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    /// `{"frame":12,"flexion":31.5,...}`, rotations in degrees and translations in mm.
+    #[default]
+    Json,
+    /// An [`OSC_ADDRESS`] message with the frame as an int32 followed by the degrees of freedom
+    /// as float32, in the order of [`Motion::DOF_NAMES`].
+    Osc,
+}
+
+#[derive(Serialize)]
+struct Packet<'a> {
+    frame: usize,
+    #[serde(flatten)]
+    motion: &'a Motion,
+}
+
+// OSC strings are null terminated and padded to 4 bytes
+fn osc_string(bytes: &mut Vec<u8>, s: &str) {
+    bytes.extend_from_slice(s.as_bytes());
+    bytes.resize((bytes.len() / 4 + 1) * 4, 0);
+}
+
+impl Encoding {
+    pub fn encode(self, frame: usize, motion: &Motion) -> Vec<u8> {
+        match self {
+            Encoding::Json => serde_json::to_vec(&Packet { frame, motion }).expect("motions serialize to JSON"),
+            Encoding::Osc => {
+                let mut bytes = Vec::with_capacity(48);
+                osc_string(&mut bytes, OSC_ADDRESS);
+                osc_string(&mut bytes, ",iffffff");
+                bytes.extend_from_slice(&(frame as i32).to_be_bytes());
+                for dof in motion.dofs() {
+                    bytes.extend_from_slice(&dof.to_be_bytes());
+                }
+                bytes
+            }
+        }
+    }
+}
+
+/// Sends motions to one address, such as a display on the same machine.
+#[derive(Debug)]
+pub struct Broadcaster {
+    socket: UdpSocket,
+    encoding: Encoding,
+}
+
+impl Broadcaster {
+    /// Sends to `target`, e.g. `127.0.0.1:9000`, from any free local port.
+    pub fn new<A: ToSocketAddrs>(target: A) -> Result<Self> {
+        let target = target
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address to send to"))?;
+        let local: SocketAddr = match target {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(target)?;
+        Ok(Self {
+            socket,
+            encoding: Encoding::default(),
+        })
+    }
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Sends one motion. Nobody listening yet is not an error, so the receiver can be started
+    /// and restarted during a session.
+    pub fn send(&self, frame: usize, motion: &Motion) -> Result<()> {
+        match self.socket.send(&self.encoding.encode(frame, motion)) {
+            Err(e) if e.kind() != io::ErrorKind::ConnectionRefused => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sends motions as they arrive, skipping gaps, and returns the number of frames. Stops at
+    /// the first error.
+    pub fn broadcast<I: Iterator<Item = Result<Option<Motion>>>>(&self, motions: I) -> Result<usize> {
+        let mut count = 0;
+        for (i, motion) in motions.enumerate() {
+            if let Some(m) = motion? {
+                self.send(i, &m)?;
+            }
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sends_json_and_osc() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let broadcaster = Broadcaster::new(receiver.local_addr().unwrap()).unwrap();
+        let motion = Motion::from_dofs([30.0, 5.0, -2.0, 1.5, 0.0, -1.0]);
+        let motions = [Ok(Some(motion)), Ok(None), Ok(Some(motion))];
+        assert_eq!(broadcaster.broadcast(motions.into_iter()).unwrap(), 3);
+
+        let mut buffer = [0; 512];
+        let n = receiver.recv(&mut buffer).unwrap();
+        let packet: serde_json::Value = serde_json::from_slice(&buffer[..n]).unwrap();
+        assert_eq!(packet["frame"], 0);
+        assert_eq!(packet["flexion"], 30.0);
+        assert_eq!(packet["lateral"], -1.0);
+        // The gap is skipped
+        let n = receiver.recv(&mut buffer).unwrap();
+        let packet: serde_json::Value = serde_json::from_slice(&buffer[..n]).unwrap();
+        assert_eq!(packet["frame"], 2);
+
+        let broadcaster = broadcaster.with_encoding(Encoding::Osc);
+        broadcaster.send(7, &motion).unwrap();
+        let n = receiver.recv(&mut buffer).unwrap();
+        assert_eq!(n, 16 + 12 + 4 + 24);
+        assert_eq!(&buffer[..16], b"/knee/motion\0\0\0\0");
+        assert_eq!(&buffer[16..28], b",iffffff\0\0\0\0");
+        assert_eq!(buffer[28..32], 7i32.to_be_bytes());
+        assert_eq!(buffer[32..36], 30.0f32.to_be_bytes());
+    }
+
+    #[test]
+    fn tolerates_missing_receiver() {
+        let closed = UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let broadcaster = Broadcaster::new(closed).unwrap();
+        let motion = Motion::from_dofs([0.0; 6]);
+        for frame in 0..3 {
+            broadcaster.send(frame, &motion).unwrap();
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::bone_to_tracker::Side;
+use crate::mesh::Mesh;
+use crate::{Error, FemurModel, Result, TibiaModel};
+
+/// Contents of `config.toml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// Tool labels per tracking system, e.g. `label.polaris`.
+    pub label: HashMap<String, Labels>,
+    pub config: Settings,
+    /// Directory relative paths are resolved against.
+    #[serde(skip)]
+    root: PathBuf,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Labels {
+    pub tibia: String,
+    pub femur: String,
+    pub patella: String,
+    pub probe: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Settings {
+    pub stl: StlPaths,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StlPaths {
+    pub tibia_left: PathBuf,
+    pub tibia_right: PathBuf,
+    pub femur_left: PathBuf,
+    pub femur_right: PathBuf,
+}
+
+impl StlPaths {
+    pub fn tibia(&self, side: Side) -> &Path {
+        match side {
+            Side::Right => &self.tibia_right,
+            Side::Left => &self.tibia_left,
+        }
+    }
+    pub fn femur(&self, side: Side) -> &Path {
+        match side {
+            Side::Right => &self.femur_right,
+            Side::Left => &self.femur_left,
+        }
+    }
+}
+
+impl Config {
+    pub fn from_toml(s: &str) -> Result<Self> {
+        toml::from_str(s).map_err(|e| Error::Format(e.to_string()))
+    }
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut config = Self::from_toml(&std::fs::read_to_string(path)?)?;
+        config.root = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(config)
+    }
+    pub fn labels(&self, system: &str) -> Option<&Labels> {
+        self.label.get(system)
+    }
+    pub fn femur_model(&self, side: Side) -> Result<Mesh<FemurModel>> {
+        Mesh::load(self.root.join(self.config.stl.femur(side)))
+    }
+    pub fn tibia_model(&self, side: Side) -> Result<Mesh<TibiaModel>> {
+        Mesh::load(self.root.join(self.config.stl.tibia(side)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reads_repository_config() {
+        let config = Config::load(concat!(env!("CARGO_MANIFEST_DIR"), "/config.toml")).unwrap();
+        assert_eq!(config.labels("polaris").unwrap().femur, "Y");
+        assert_eq!(config.labels("certus").unwrap().tibia, "tibia");
+        assert_eq!(config.config.stl.tibia(Side::Right), Path::new("models/tibia-right-test.stl"));
+        assert_eq!(config.config.stl.femur(Side::Left), Path::new("models/femur-left.stl"));
+        // The referenced models are not checked in
+        assert!(config.femur_model(Side::Left).is_err());
+    }
+}
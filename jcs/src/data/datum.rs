@@ -1,11 +1,14 @@
 use std::marker::PhantomData;
 
+use input::Frame;
+
 use crate::{bone_to_tracker::Global, transform::{gT, IsFrameOfReference, Transform}, Marker};
 
-use super::{ProbeData, ProbeRawData};
+use super::{ProbeData, ProbeRawData, Quality, QualityThresholds};
 
 pub struct Datum<M: Marker> {
     data: ProbeData,
+    quality: Quality,
     marker: PhantomData<M>
 }
 
@@ -18,11 +21,30 @@ impl <'a, M: Marker + IsFrameOfReference> From<ProbeRawData<'a>> for Datum<M> {
 impl<M: Marker + IsFrameOfReference> Datum<M> {
     // #[cfg(test)]
     pub fn new(data: ProbeData) -> Self {
+        Self::with_quality(data, Quality::default())
+    }
+    pub fn with_quality(data: ProbeData, quality: Quality) -> Self {
         Self {
             data,
+            quality,
             marker: PhantomData,
         }
     }
+    /// Sample of the tool matching `label` in `frame`, if it reported a pose.
+    pub fn from_frame(frame: &Frame, label: &str) -> Option<Self> {
+        let record = frame.tool(label)?;
+        let data = ProbeData::from_record(record)?;
+        Some(Self::with_quality(data, record.into()))
+    }
+    pub(crate) fn data(&self) -> &ProbeData {
+        &self.data
+    }
+    pub fn quality(&self) -> &Quality {
+        &self.quality
+    }
+    pub fn is_valid(&self, thresholds: &QualityThresholds) -> bool {
+        self.quality.is_valid(thresholds)
+    }
     pub fn to_transform(&self) -> gT<M> {
         Transform::<Global, M>::new(self.data.to_transform())
     }
@@ -71,9 +93,9 @@ mod datum_to_tracker {
         let t_data = ProbeRawData::new(name, label, 0.0347, -0.1902, 0.0599, 0.9793, 128.2, 205.321, -2050.397);
 
 
-        let femur = Femur::new(side, fm.into(), fl.into(), fp.into(), femur_tracker_data.into());
+        let femur = Femur::new(side, fm.into(), fl.into(), fp.into(), femur_tracker_data.into()).unwrap();
 
-        let tibia = Tibia::new(side, tm.into(), tl.into(), td.into(), tibia_tracker_data.into());
+        let tibia = Tibia::new(side, tm.into(), tl.into(), td.into(), tibia_tracker_data.into()).unwrap();
 
 
         let g_t_ti = tibia.take_datum(t_data.into());
@@ -82,7 +104,7 @@ mod datum_to_tracker {
         // println!("femur in global {}", g_t_fi);
         // println!("tibia in global {}", g_t_ti);
 
-        let f_t_t = g_t_fi.mldivide(&g_t_ti); // Tibia in femoral frame of reference
+        let f_t_t = g_t_fi.mldivide(&g_t_ti).unwrap(); // Tibia in femoral frame of reference
 
         println!("Tibia in femur {}", f_t_t);
         // println!("Rotation: {}", f_t_t.rotation());
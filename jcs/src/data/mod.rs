@@ -1,5 +1,7 @@
 mod datum;
 mod probe_data;
+mod quality;
 
 pub use probe_data::{ProbeRawData, ProbeData};
 pub use datum::Datum;
+pub use quality::{Quality, QualityThresholds};
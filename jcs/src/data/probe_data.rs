@@ -1,6 +1,9 @@
+use input::ToolRecord;
 use nalgebra as na;
+use serde::{Deserialize, Serialize};
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Deserialize, Serialize)]
+#[serde(into = "ProbeSample", from = "ProbeSample")]
 pub struct ProbeData {
     name: String, 
     label: String,
@@ -15,6 +18,35 @@ impl std::fmt::Display for ProbeData {
     }
 }
 
+// Serialized form with the quaternion in NDI order
+#[derive(Deserialize, Serialize)]
+struct ProbeSample {
+    name: String,
+    label: String,
+    translation: [f32; 3],
+    /// `[q0, qx, qy, qz]`
+    rotation: [f32; 4],
+}
+
+impl From<ProbeData> for ProbeSample {
+    fn from(value: ProbeData) -> Self {
+        let (t, q) = (value.translation, value.rotation);
+        Self {
+            name: value.name,
+            label: value.label,
+            translation: [t.x, t.y, t.z],
+            rotation: [q.w, q.i, q.j, q.k],
+        }
+    }
+}
+
+impl From<ProbeSample> for ProbeData {
+    fn from(value: ProbeSample) -> Self {
+        let ([x, y, z], [q0, qx, qy, qz]) = (value.translation, value.rotation);
+        ProbeRawData::new(&value.name, &value.label, q0, qx, qy, qz, x, y, z).into()
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct ProbeRawData <'a>{
     name: &'a str,
@@ -29,7 +61,8 @@ pub struct ProbeRawData <'a>{
 }
 
 impl <'a> ProbeRawData <'a> {
-    pub(crate) fn new(name: &'a str, label: &'a str, q0: f32, qx: f32, qy: f32, qz: f32, x: f32, y: f32, z: f32) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(name: &'a str, label: &'a str, q0: f32, qx: f32, qy: f32, qz: f32, x: f32, y: f32, z: f32) -> Self {
         Self {q0, qx, qy, qz, x, y, z, name, label}
     }
 }
@@ -64,6 +97,21 @@ impl ProbeData {
             label: probe_data.label.to_string(),
         }
     }
+    pub fn from_record(record: &ToolRecord) -> Option<Self> {
+        let [q0, qx, qy, qz] = record.rotation?;
+        let [x, y, z] = record.translation?;
+        let raw = ProbeRawData::new(&record.port.name, &record.port.serial, q0, qx, qy, qz, x, y, z);
+        Some(raw.into())
+    }
+    /// Same probe and label at another pose.
+    pub(crate) fn with_pose(&self, translation: na::Vector3<f32>, rotation: na::UnitQuaternion<f32>) -> Self {
+        Self {
+            translation,
+            rotation,
+            name: self.name.clone(),
+            label: self.label.clone(),
+        }
+    }
     pub fn to_transform(&self) -> na::Transform3<f32> {
         let rotation = self.rotation().to_homogeneous();
         let translation = na::Matrix4::new_translation(self.translation());
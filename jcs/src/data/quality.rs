@@ -0,0 +1,54 @@
+use input::{State, ToolRecord};
+
+/// Tracking quality of a single tool sample.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quality {
+    pub state: State,
+    pub error: Option<f32>,
+    pub markers: Option<u32>,
+}
+
+impl Default for Quality {
+    // Data entered by hand carries no quality information and is trusted
+    fn default() -> Self {
+        Self {
+            state: State::Ok,
+            error: None,
+            markers: None,
+        }
+    }
+}
+
+impl From<&ToolRecord> for Quality {
+    fn from(record: &ToolRecord) -> Self {
+        Self {
+            state: record.state,
+            error: record.error,
+            markers: Some(record.markers),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityThresholds {
+    /// Maximum RMS marker fit error in mm.
+    pub max_error: f32,
+    pub min_markers: u32,
+}
+
+impl Default for QualityThresholds {
+    fn default() -> Self {
+        Self {
+            max_error: 1.0,
+            min_markers: 3,
+        }
+    }
+}
+
+impl Quality {
+    pub fn is_valid(&self, thresholds: &QualityThresholds) -> bool {
+        self.state == State::Ok
+            && self.error.is_none_or(|e| e <= thresholds.max_error)
+            && self.markers.is_none_or(|m| m >= thresholds.min_markers)
+    }
+}
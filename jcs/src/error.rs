@@ -0,0 +1,79 @@
+use std::fmt;
+use std::io;
+
+/// Everything that can go wrong turning recordings into kinematics.
+#[derive(Debug)]
+pub enum Error {
+    Input(input::Error),
+    Io(io::Error),
+    /// Malformed TOML or STL contents.
+    Format(String),
+    /// No tool in the recording matches the label.
+    MissingTool(String),
+    /// Landmarks too close together or collinear to define the anatomical frame of the named bone.
+    DegenerateLandmarks(String),
+    SingularTransform,
+    /// Series that must be sampled on the same frames differ in length.
+    LengthMismatch { expected: usize, found: usize },
+    /// A frame, by index, without a timestamp.
+    MissingTime(usize),
+    /// A filter cutoff outside (0, Nyquist) for the sampling rate, both in Hz.
+    InvalidCutoff { cutoff: f64, sample_rate: f64 },
+    /// Data tagged with one frame of reference loaded as another.
+    InvalidFrame { expected: String, found: String },
+    /// A mesh without faces where a surface is needed.
+    EmptyMesh,
+    /// A trial name that cannot be used as an output file name, with the reason.
+    InvalidTrialName { name: String, reason: &'static str },
+    /// Worker threads could not be started.
+    ThreadPool(String),
+    /// A sampled estimate asked for fewer samples than it needs, with the number given.
+    TooFewSamples(usize),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Input(e) => write!(f, "{e}"),
+            Error::Io(e) => write!(f, "{e}"),
+            Error::Format(reason) => f.write_str(reason),
+            Error::MissingTool(label) => write!(f, "no tool matches `{label}`"),
+            Error::DegenerateLandmarks(bone) => write!(f, "landmarks do not define a frame for {bone}"),
+            Error::SingularTransform => f.write_str("transform is not invertible"),
+            Error::LengthMismatch { expected, found } => write!(f, "expected {expected} samples, found {found}"),
+            Error::MissingTime(frame) => write!(f, "frame {frame} has no timestamp"),
+            Error::InvalidCutoff { cutoff, sample_rate } => {
+                write!(f, "cutoff of {cutoff} Hz is not below the Nyquist frequency of {sample_rate} Hz sampling")
+            }
+            Error::InvalidFrame { expected, found } => write!(f, "frame mismatch: expected {expected}, found {found}"),
+            Error::EmptyMesh => f.write_str("mesh has no faces"),
+            Error::InvalidTrialName { name, reason } => write!(f, "trial name {name:?} {reason}"),
+            Error::ThreadPool(reason) => write!(f, "cannot start worker threads: {reason}"),
+            Error::TooFewSamples(samples) => write!(f, "need at least two samples, got {samples}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Input(e) => Some(e),
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<input::Error> for Error {
+    fn from(e: input::Error) -> Self {
+        Error::Input(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
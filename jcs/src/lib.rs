@@ -2,18 +2,34 @@
 #[macro_use]
 extern crate approx;
 
+pub mod analysis;
+#[cfg(feature = "knee")]
+pub mod batch;
 mod bone_to_tracker;
-mod data;
+pub mod broadcast;
+#[cfg(feature = "knee")]
+pub mod config;
+pub mod data;
+mod error;
+pub mod mesh;
 mod solvers;
+pub mod series;
+pub mod stream;
+#[cfg(feature = "knee")]
+pub mod session;
+#[cfg(feature = "knee")]
+pub mod synthetic;
 pub mod transform;
 pub mod prelude;
 
 use std::marker::PhantomData;
 
-use bone_to_tracker::{Landmark, Lateral, Medial, ProximalDistal, Side};
+use bone_to_tracker::{Landmark, Lateral, Medial, ProximalDistal};
+use mesh::Mesh;
 use transform::{gT, IsFrameOfReference};
 
 pub use crate::prelude::*;
+pub use error::{Error, Result};
 // pub use transform::Transform;
 // pub use bone_to_tracker::Kinematics;
 
@@ -29,15 +45,33 @@ pub struct RigidBody<const ID: usize> {
     lateral: Landmark<RigidBody<ID>, Lateral>,
     proximal_distal: Landmark<RigidBody<ID>, ProximalDistal>,
     tracker: gT<Tracker<RigidBody<ID>>>,
+    model: Option<Mesh<Model<RigidBody<ID>>>>,
 }
 
 #[derive(Debug)]
 pub struct Tracker<RB: IsFrameOfReference>(PhantomData<RB>);
 
-impl<const ID: usize> IsFrameOfReference for RigidBody<ID> {}
+/// Frame of a bone model, e.g. the CT coordinates of an STL.
+#[derive(Debug)]
+pub struct Model<RB: IsFrameOfReference>(PhantomData<RB>);
+
+impl<const ID: usize> IsFrameOfReference for RigidBody<ID> {
+    fn name() -> String {
+        format!("RigidBody<{ID}>")
+    }
+}
 impl<const ID: usize> IsRigidBody for RigidBody<ID> {}
 
 impl<RB: IsFrameOfReference> Marker for Tracker<RB> {}
-impl<RB: IsFrameOfReference> IsFrameOfReference for Tracker<RB> {}
+impl<RB: IsFrameOfReference> IsFrameOfReference for Tracker<RB> {
+    fn name() -> String {
+        format!("Tracker<{}>", RB::name())
+    }
+}
+impl<RB: IsFrameOfReference> IsFrameOfReference for Model<RB> {
+    fn name() -> String {
+        format!("Model<{}>", RB::name())
+    }
+}
 
 impl Marker for Probe {}
@@ -0,0 +1,156 @@
+use nalgebra as na;
+
+use super::closest::closest_point_on_triangle;
+
+const LEAF_SIZE: usize = 4;
+
+#[derive(Debug, Clone, Copy)]
+struct Aabb {
+    min: na::Point3<f32>,
+    max: na::Point3<f32>,
+}
+
+impl Aabb {
+    fn empty() -> Self {
+        Self {
+            min: na::Point3::from([f32::INFINITY; 3]),
+            max: na::Point3::from([f32::NEG_INFINITY; 3]),
+        }
+    }
+    fn grow(&mut self, p: &na::Point3<f32>) {
+        self.min = self.min.inf(p);
+        self.max = self.max.sup(p);
+    }
+    fn distance_squared(&self, p: &na::Point3<f32>) -> f32 {
+        let d = (self.min - p).sup(&(p - self.max)).sup(&na::Vector3::zeros());
+        d.norm_squared()
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Leaf { bounds: Aabb, start: usize, end: usize },
+    Branch { bounds: Aabb, left: usize, right: usize },
+}
+
+impl Node {
+    fn bounds(&self) -> &Aabb {
+        match self {
+            Node::Leaf { bounds, .. } | Node::Branch { bounds, .. } => bounds,
+        }
+    }
+}
+
+/// Bounding volume hierarchy over the faces of a mesh.
+#[derive(Debug, Clone)]
+pub(crate) struct Bvh {
+    nodes: Vec<Node>,
+    faces: Vec<usize>,
+    triangles: Vec<[na::Point3<f32>; 3]>,
+}
+
+impl Bvh {
+    pub(crate) fn new(triangles: Vec<[na::Point3<f32>; 3]>) -> Self {
+        let mut bvh = Self {
+            nodes: Vec::new(),
+            faces: (0..triangles.len()).collect(),
+            triangles,
+        };
+        if !bvh.faces.is_empty() {
+            bvh.build(0, bvh.faces.len());
+        }
+        bvh
+    }
+
+    fn build(&mut self, start: usize, end: usize) -> usize {
+        let mut bounds = Aabb::empty();
+        let mut centroids = Aabb::empty();
+        for &face in &self.faces[start..end] {
+            let t = &self.triangles[face];
+            t.iter().for_each(|p| bounds.grow(p));
+            centroids.grow(&centroid(t));
+        }
+        let index = self.nodes.len();
+        if end - start <= LEAF_SIZE {
+            self.nodes.push(Node::Leaf { bounds, start, end });
+            return index;
+        }
+        // Median split along the longest axis of the centroids
+        let axis = (centroids.max - centroids.min).imax();
+        let triangles = &self.triangles;
+        let mid = (start + end) / 2;
+        self.faces[start..end].select_nth_unstable_by(mid - start, |a, b| {
+            centroid(&triangles[*a])[axis].total_cmp(&centroid(&triangles[*b])[axis])
+        });
+        self.nodes.push(Node::Leaf { bounds, start, end });
+        let left = self.build(start, mid);
+        let right = self.build(mid, end);
+        self.nodes[index] = Node::Branch { bounds, left, right };
+        index
+    }
+
+    /// Closest point on any face to `p`, with the face index.
+    pub(crate) fn closest_point(&self, p: &na::Point3<f32>) -> Option<(na::Point3<f32>, usize)> {
+        let mut best: Option<(na::Point3<f32>, usize, f32)> = None;
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let Some(node) = self.nodes.get(index) else { break };
+            if best.is_some_and(|(_, _, d)| node.bounds().distance_squared(p) >= d) {
+                continue;
+            }
+            match node {
+                Node::Leaf { start, end, .. } => {
+                    for &face in &self.faces[*start..*end] {
+                        let [a, b, c] = &self.triangles[face];
+                        let q = closest_point_on_triangle(p, a, b, c);
+                        let d = (q - p).norm_squared();
+                        if best.is_none_or(|(_, _, best)| d < best) {
+                            best = Some((q, face, d));
+                        }
+                    }
+                }
+                Node::Branch { left, right, .. } => {
+                    // Visit the nearer child first
+                    let (l, r) = (self.nodes[*left].bounds().distance_squared(p), self.nodes[*right].bounds().distance_squared(p));
+                    if l < r {
+                        stack.extend([*right, *left]);
+                    } else {
+                        stack.extend([*left, *right]);
+                    }
+                }
+            }
+        }
+        best.map(|(q, face, _)| (q, face))
+    }
+}
+
+fn centroid(t: &[na::Point3<f32>; 3]) -> na::Point3<f32> {
+    na::Point3::from((t[0].coords + t[1].coords + t[2].coords) / 3.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bone_to_tracker::Tibia;
+    use crate::mesh::ellipsoid;
+    use crate::Model;
+
+    #[test]
+    fn matches_exhaustive_search() {
+        let mesh = ellipsoid::<Model<Tibia>>(30.0, 20.0, 10.0, 20);
+        let bvh = Bvh::new((0..mesh.faces().len()).map(|f| mesh.triangle(f)).collect());
+        for i in 0..200 {
+            let t = i as f32;
+            let p = na::Point3::new(50.0 * (0.37 * t).sin(), 35.0 * (0.71 * t).cos(), 20.0 * (1.3 * t).sin());
+            let (fast, _) = bvh.closest_point(&p).unwrap();
+            let exhaustive = (0..mesh.faces().len())
+                .map(|f| {
+                    let [a, b, c] = mesh.triangle(f);
+                    closest_point_on_triangle(&p, &a, &b, &c)
+                })
+                .min_by(|x, y| (x - p).norm_squared().total_cmp(&(y - p).norm_squared()))
+                .unwrap();
+            assert_relative_eq!((fast - p).norm(), (exhaustive - p).norm(), epsilon = 1e-4);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use nalgebra as na;
+
+use super::Mesh;
+use crate::transform::IsFrameOfReference;
+use crate::{Error, Result};
+
+// Barycentric weight below which a closest point is taken to lie on an edge or vertex
+const FEATURE_TOLERANCE: f32 = 1e-4;
+
+/// Angle-weighted vertex normals and edge normals (Bærentzen and Aanæs 2005), which give the
+/// correct inside/outside sign wherever on the surface the closest point lies.
+#[derive(Debug)]
+pub(crate) struct PseudoNormals {
+    vertices: Vec<na::Vector3<f32>>,
+    // Keyed by the sorted vertex indices of the edge
+    edges: HashMap<(usize, usize), na::Vector3<f32>>,
+}
+
+impl PseudoNormals {
+    fn new<F: IsFrameOfReference>(mesh: &Mesh<F>) -> Self {
+        let mut vertices = vec![na::Vector3::zeros(); mesh.vertices.len()];
+        let mut edges = HashMap::new();
+        for (face, normal) in mesh.faces.iter().zip(&mesh.normals) {
+            for i in 0..3 {
+                let (v, next, prev) = (face[i], face[(i + 1) % 3], face[(i + 2) % 3]);
+                let (a, b) = (mesh.vertices[next] - mesh.vertices[v], mesh.vertices[prev] - mesh.vertices[v]);
+                vertices[v] += normal * a.angle(&b);
+                *edges.entry(edge(v, next)).or_insert_with(na::Vector3::zeros) += normal;
+            }
+        }
+        Self { vertices, edges }
+    }
+}
+
+fn edge(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+/// Weights of `a`, `b` and `c` giving `p` in the plane of the triangle.
+fn barycentric(p: &na::Point3<f32>, a: &na::Point3<f32>, b: &na::Point3<f32>, c: &na::Point3<f32>) -> [f32; 3] {
+    let (v0, v1, v2) = (b - a, c - a, p - a);
+    let (d00, d01, d11) = (v0.dot(&v0), v0.dot(&v1), v1.dot(&v1));
+    let (d20, d21) = (v2.dot(&v0), v2.dot(&v1));
+    let denom = d00 * d11 - d01 * d01;
+    let v = (d11 * d20 - d01 * d21) / denom;
+    let w = (d00 * d21 - d01 * d20) / denom;
+    [1.0 - v - w, v, w]
+}
+
+/// Closest point to `p` on the triangle `abc` (Ericson, Real-Time Collision Detection 5.1.5).
+pub fn closest_point_on_triangle(
+    p: &na::Point3<f32>,
+    a: &na::Point3<f32>,
+    b: &na::Point3<f32>,
+    c: &na::Point3<f32>,
+) -> na::Point3<f32> {
+    let (ab, ac, ap) = (b - a, c - a, p - a);
+    let (d1, d2) = (ab.dot(&ap), ac.dot(&ap));
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return *a;
+    }
+    let bp = p - b;
+    let (d3, d4) = (ab.dot(&bp), ac.dot(&bp));
+    if d3 >= 0.0 && d4 <= d3 {
+        return *b;
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+    let cp = p - c;
+    let (d5, d6) = (ab.dot(&cp), ac.dot(&cp));
+    if d6 >= 0.0 && d5 <= d6 {
+        return *c;
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+    let denom = 1.0 / (va + vb + vc);
+    a + ab * (vb * denom) + ac * (vc * denom)
+}
+
+impl<F: IsFrameOfReference> Mesh<F> {
+    /// Closest point on the surface to `p` and the face it lies on. Fails on a mesh without faces.
+    pub fn closest_point(&self, p: &na::Point3<f32>) -> Result<(na::Point3<f32>, usize)> {
+        self.bvh().closest_point(p).ok_or(Error::EmptyMesh)
+    }
+    /// Closest point and the distance to it, negative when `p` lies inside the surface. The sign
+    /// comes from the pseudo-normal of the face, edge or vertex the closest point lies on.
+    pub fn signed_closest_point(&self, p: &na::Point3<f32>) -> Result<(na::Point3<f32>, f32)> {
+        let (q, face) = self.closest_point(p)?;
+        let distance = (p - q).norm();
+        Ok((q, if (p - q).dot(&self.pseudo_normal(face, &q)) < 0.0 { -distance } else { distance }))
+    }
+
+    fn pseudo_normal(&self, face: usize, q: &na::Point3<f32>) -> na::Vector3<f32> {
+        let normals = self.pseudo_normals.get_or_init(|| PseudoNormals::new(self));
+        let [a, b, c] = self.triangle(face);
+        let weights = barycentric(q, &a, &b, &c);
+        let indices = self.faces[face];
+        // Degenerate triangles give NaN weights and fall back to the face normal
+        let on: Vec<usize> = (0..3).filter(|&i| weights[i] > FEATURE_TOLERANCE).map(|i| indices[i]).collect();
+        match on[..] {
+            [v] => normals.vertices[v],
+            // Open meshes have edges with a single face
+            [a, b] => normals.edges.get(&edge(a, b)).copied().unwrap_or(self.normals[face]),
+            _ => self.normals[face],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn projects_onto_regions() {
+        let (a, b, c) = (na::Point3::origin(), na::Point3::new(1.0, 0.0, 0.0), na::Point3::new(0.0, 1.0, 0.0));
+        let closest = |x: f32, y: f32, z: f32| closest_point_on_triangle(&na::Point3::new(x, y, z), &a, &b, &c);
+        assert_relative_eq!(closest(0.2, 0.2, 5.0), na::Point3::new(0.2, 0.2, 0.0));
+        assert_eq!(closest(-1.0, -1.0, 0.0), a);
+        assert_eq!(closest(3.0, -1.0, 1.0), b);
+        assert_relative_eq!(closest(0.5, -2.0, 0.0), na::Point3::new(0.5, 0.0, 0.0));
+        assert_relative_eq!(closest(1.0, 1.0, 0.0), na::Point3::new(0.5, 0.5, 0.0));
+    }
+
+    #[test]
+    fn signs_distances_at_sharp_edges() {
+        // Thin wedge: the base and the sloping top meet at a sharp edge from b to c
+        use crate::bone_to_tracker::Global;
+        use crate::mesh::Triangle;
+        let (a, b, c, d) = (
+            na::Point3::origin(),
+            na::Point3::new(10.0, 0.0, 0.0),
+            na::Point3::new(0.0, 10.0, 0.0),
+            na::Point3::new(0.0, 0.0, 0.5),
+        );
+        let triangle = |vertices| Triangle { normal: None, vertices };
+        let wedge = Mesh::<Global>::from_triangles(vec![
+            triangle([a, c, b]),
+            triangle([b, c, d]),
+            triangle([a, b, d]),
+            triangle([a, d, c]),
+        ]);
+        // Beyond the edge, closer to the plane of the top than that of the base
+        for p in [na::Point3::new(6.0, 6.0, 0.1), na::Point3::new(6.0, 6.0, -0.05), na::Point3::new(12.0, -0.5, 0.0)] {
+            let (_, distance) = wedge.signed_closest_point(&p).unwrap();
+            assert!(distance > 0.0, "{p} is outside, got {distance}");
+        }
+        let (_, distance) = wedge.signed_closest_point(&na::Point3::new(2.0, 2.0, 0.1)).unwrap();
+        assert!(distance < 0.0);
+    }
+
+    #[test]
+    fn rejects_inconsistent_meshes() {
+        use crate::bone_to_tracker::Global;
+        let origin = na::Point3::origin();
+        let empty = Mesh::<Global>::new(vec![origin], Vec::new(), Vec::new()).unwrap();
+        assert!(matches!(empty.closest_point(&origin), Err(Error::EmptyMesh)));
+        let error = Mesh::<Global>::new(vec![origin; 3], vec![[0, 1, 2]], Vec::new()).unwrap_err();
+        assert!(matches!(error, Error::LengthMismatch { expected: 1, found: 0 }));
+        assert!(Mesh::<Global>::new(vec![origin; 3], vec![[0, 1, 3]], vec![na::Vector3::z()]).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use super::Mesh;
+use crate::bone_to_tracker::Global;
+use crate::series::PoseSeries;
+use crate::transform::{IsFrameOfReference, Transform};
+use crate::{Model, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshFormat {
+    Obj,
+    Ply,
+}
+
+impl MeshFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            MeshFormat::Obj => "obj",
+            MeshFormat::Ply => "ply",
+        }
+    }
+}
+
+impl<F: IsFrameOfReference> Mesh<F> {
+    /// The same surface expressed in `G`.
+    pub fn transformed<G: IsFrameOfReference>(&self, transform: &Transform<G, F>) -> Mesh<G> {
+        let vertices = self.vertices.iter().map(|v| transform.transform_point(v)).collect();
+        let normals = self.normals.iter().map(|n| transform.inner().transform_vector(n)).collect();
+        Mesh::from_parts(vertices, self.faces.clone(), normals)
+    }
+}
+
+impl<RB: IsFrameOfReference> Mesh<Model<RB>> {
+    /// The registered model in global for every frame of `poses`; `None` where the bone is missing.
+    pub fn animate<'a>(
+        &'a self,
+        registration: &'a Transform<RB, Model<RB>>,
+        poses: &'a PoseSeries<RB>,
+    ) -> impl Iterator<Item = Option<Mesh<Global>>> + 'a {
+        poses.poses().iter().map(move |pose| pose.map(|pose| self.transformed(&(pose * *registration))))
+    }
+}
+
+/// Writes named meshes as separate objects of one OBJ file.
+pub fn write_obj<W: Write>(writer: &mut W, meshes: &[(&str, &Mesh<Global>)]) -> io::Result<()> {
+    // OBJ indices are global to the file and 1-based
+    let (mut offset, mut normal_offset) = (1, 1);
+    for (name, mesh) in meshes {
+        writeln!(writer, "o {name}")?;
+        for v in mesh.vertices() {
+            writeln!(writer, "v {} {} {}", v.x, v.y, v.z)?;
+        }
+        for n in mesh.normals() {
+            writeln!(writer, "vn {} {} {}", n.x, n.y, n.z)?;
+        }
+        for (i, [a, b, c]) in mesh.faces().iter().enumerate() {
+            let n = normal_offset + i;
+            writeln!(writer, "f {}//{n} {}//{n} {}//{n}", a + offset, b + offset, c + offset)?;
+        }
+        offset += mesh.vertices().len();
+        normal_offset += mesh.normals().len();
+    }
+    Ok(())
+}
+
+/// Writes the meshes merged into a single ASCII PLY surface.
+pub fn write_ply<W: Write>(writer: &mut W, meshes: &[(&str, &Mesh<Global>)]) -> io::Result<()> {
+    let vertices: usize = meshes.iter().map(|(_, m)| m.vertices().len()).sum();
+    let faces: usize = meshes.iter().map(|(_, m)| m.faces().len()).sum();
+    writeln!(writer, "ply\nformat ascii 1.0")?;
+    for (name, _) in meshes {
+        writeln!(writer, "comment object {name}")?;
+    }
+    writeln!(writer, "element vertex {vertices}\nproperty float x\nproperty float y\nproperty float z")?;
+    writeln!(writer, "element face {faces}\nproperty list uchar int vertex_indices\nend_header")?;
+    for (_, mesh) in meshes {
+        for v in mesh.vertices() {
+            writeln!(writer, "{} {} {}", v.x, v.y, v.z)?;
+        }
+    }
+    let mut offset = 0;
+    for (_, mesh) in meshes {
+        for [a, b, c] in mesh.faces() {
+            writeln!(writer, "3 {} {} {}", a + offset, b + offset, c + offset)?;
+        }
+        offset += mesh.vertices().len();
+    }
+    Ok(())
+}
+
+/// Writes one file per frame into `dir` (`frame_00000.obj`, ...), each holding every bone
+/// present in that frame. `bones` pairs a name with the output of [`Mesh::animate`].
+pub fn export_trial(dir: &Path, format: MeshFormat, bones: &[(&str, Vec<Option<Mesh<Global>>>)]) -> Result<usize> {
+    std::fs::create_dir_all(dir)?;
+    let frames = bones.iter().map(|(_, f)| f.len()).max().unwrap_or(0);
+    for i in 0..frames {
+        let meshes: Vec<(&str, &Mesh<Global>)> = bones
+            .iter()
+            .filter_map(|(name, frames)| Some((*name, frames.get(i)?.as_ref()?)))
+            .collect();
+        let path = dir.join(format!("frame_{i:05}.{}", format.extension()));
+        let mut writer = io::BufWriter::new(std::fs::File::create(path)?);
+        match format {
+            MeshFormat::Obj => write_obj(&mut writer, &meshes)?,
+            MeshFormat::Ply => write_ply(&mut writer, &meshes)?,
+        }
+        writer.flush()?;
+    }
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra as na;
+
+    use super::*;
+    use crate::bone_to_tracker::{Femur, Tibia};
+    use crate::mesh::ellipsoid;
+
+    fn series<RB: IsFrameOfReference>(n: usize, missing: usize) -> PoseSeries<RB> {
+        let time = (0..n).map(|i| i as f64).collect();
+        let poses = (0..n)
+            .map(|i| {
+                let translation = na::Vector3::new(i as f32 * 10.0, 0.0, 0.0);
+                (i != missing).then(|| Transform::from_parts(&translation, &na::UnitQuaternion::identity()))
+            })
+            .collect();
+        PoseSeries::new(time, poses).unwrap()
+    }
+
+    fn identity<A: IsFrameOfReference, B: IsFrameOfReference>() -> Transform<A, B> {
+        Transform::from_parts(&na::Vector3::zeros(), &na::UnitQuaternion::identity())
+    }
+
+    #[test]
+    fn animates_registered_models() {
+        let mesh = ellipsoid::<Model<Femur>>(10.0, 5.0, 5.0, 8);
+        let registration = Transform::<Femur, Model<Femur>>::from_parts(
+            &na::Vector3::new(0.0, 0.0, 100.0),
+            &na::UnitQuaternion::identity(),
+        );
+        let frames: Vec<_> = mesh.animate(&registration, &series(3, 1)).collect();
+        assert!(frames[1].is_none());
+        let moved = frames[2].as_ref().unwrap();
+        assert_relative_eq!(moved.vertices()[0], mesh.vertices()[0] + na::Vector3::new(20.0, 0.0, 100.0));
+    }
+
+    #[test]
+    fn exports_one_file_per_frame() {
+        let femur = ellipsoid::<Model<Femur>>(10.0, 5.0, 5.0, 6);
+        let tibia = ellipsoid::<Model<Tibia>>(8.0, 4.0, 4.0, 6);
+        let bones = [
+            ("femur", femur.animate(&identity(), &series(3, 9)).collect()),
+            ("tibia", tibia.animate(&identity(), &series(3, 2)).collect()),
+        ];
+        let dir = std::env::temp_dir().join(format!("jcs-export-{}", std::process::id()));
+        for format in [MeshFormat::Obj, MeshFormat::Ply] {
+            assert_eq!(export_trial(&dir, format, &bones).unwrap(), 3);
+        }
+        let obj = std::fs::read_to_string(dir.join("frame_00000.obj")).unwrap();
+        assert_eq!(obj.lines().filter(|l| l.starts_with("o ")).count(), 2);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), femur.faces().len() + tibia.faces().len());
+        let last = std::fs::read_to_string(dir.join("frame_00002.obj")).unwrap();
+        assert!(last.contains("o femur") && !last.contains("o tibia"));
+        let ply = std::fs::read_to_string(dir.join("frame_00001.ply")).unwrap();
+        assert!(ply.contains(&format!("element vertex {}", femur.vertices().len() + tibia.vertices().len())));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,139 @@
+mod bvh;
+mod closest;
+mod export;
+mod proximity;
+mod registration;
+mod stl;
+
+pub use closest::closest_point_on_triangle;
+pub use export::{export_trial, write_obj, write_ply, MeshFormat};
+pub use proximity::{contact_map, proximity, Proximity};
+pub use registration::{fit_rigid, icp, IcpOptions, ModelLandmarks, Registration};
+pub use stl::Triangle;
+
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use nalgebra as na;
+
+use crate::transform::IsFrameOfReference;
+use crate::{Error, Result};
+use bvh::Bvh;
+use closest::PseudoNormals;
+
+/// Triangle mesh with coordinates in the frame `F`, typically a bone model frame.
+#[derive(Debug)]
+pub struct Mesh<F: IsFrameOfReference> {
+    vertices: Vec<na::Point3<f32>>,
+    faces: Vec<[usize; 3]>,
+    normals: Vec<na::Vector3<f32>>,
+    // Built on the first closest point query
+    bvh: OnceLock<Bvh>,
+    // Built on the first signed distance query
+    pseudo_normals: OnceLock<PseudoNormals>,
+    frame: PhantomData<F>,
+}
+
+impl<F: IsFrameOfReference> Clone for Mesh<F> {
+    fn clone(&self) -> Self {
+        Self::from_parts(self.vertices.clone(), self.faces.clone(), self.normals.clone())
+    }
+}
+
+impl<F: IsFrameOfReference> Mesh<F> {
+    /// Fails unless there is one normal per face and every face refers to existing vertices.
+    pub fn new(vertices: Vec<na::Point3<f32>>, faces: Vec<[usize; 3]>, normals: Vec<na::Vector3<f32>>) -> Result<Self> {
+        if faces.len() != normals.len() {
+            return Err(Error::LengthMismatch { expected: faces.len(), found: normals.len() });
+        }
+        if let Some(i) = faces.iter().position(|f| f.iter().any(|v| *v >= vertices.len())) {
+            return Err(Error::Format(format!("face {i} refers to a vertex past the {} of the mesh", vertices.len())));
+        }
+        Ok(Self::from_parts(vertices, faces, normals))
+    }
+    // Faces and normals already known to match
+    pub(crate) fn from_parts(vertices: Vec<na::Point3<f32>>, faces: Vec<[usize; 3]>, normals: Vec<na::Vector3<f32>>) -> Self {
+        Self {
+            vertices,
+            faces,
+            normals,
+            bvh: OnceLock::new(),
+            pseudo_normals: OnceLock::new(),
+            frame: PhantomData,
+        }
+    }
+    /// Reads a binary or ASCII STL file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_stl(&std::fs::read(path)?)
+    }
+    pub fn from_stl(bytes: &[u8]) -> Result<Self> {
+        let triangles = stl::parse(bytes)?;
+        Ok(Self::from_triangles(triangles))
+    }
+    /// Builds an indexed mesh from a triangle soup, merging identical vertices.
+    pub fn from_triangles(triangles: Vec<stl::Triangle>) -> Self {
+        let mut index = std::collections::HashMap::new();
+        let mut vertices = Vec::new();
+        let mut faces = Vec::with_capacity(triangles.len());
+        let mut normals = Vec::with_capacity(triangles.len());
+        for triangle in triangles {
+            let face = triangle.vertices.map(|v| {
+                *index.entry(v.coords.map(f32::to_bits)).or_insert_with(|| {
+                    vertices.push(v);
+                    vertices.len() - 1
+                })
+            });
+            faces.push(face);
+            normals.push(triangle.normal.unwrap_or_else(|| face_normal(&triangle.vertices)));
+        }
+        Self::from_parts(vertices, faces, normals)
+    }
+    pub fn vertices(&self) -> &[na::Point3<f32>] {
+        &self.vertices
+    }
+    pub fn faces(&self) -> &[[usize; 3]] {
+        &self.faces
+    }
+    pub fn normals(&self) -> &[na::Vector3<f32>] {
+        &self.normals
+    }
+    pub fn triangle(&self, face: usize) -> [na::Point3<f32>; 3] {
+        self.faces[face].map(|i| self.vertices[i])
+    }
+    fn bvh(&self) -> &Bvh {
+        self.bvh
+            .get_or_init(|| Bvh::new((0..self.faces.len()).map(|f| self.triangle(f)).collect()))
+    }
+}
+
+fn face_normal(vertices: &[na::Point3<f32>; 3]) -> na::Vector3<f32> {
+    let [a, b, c] = vertices;
+    (b - a).cross(&(c - a)).try_normalize(f32::EPSILON).unwrap_or_else(na::Vector3::zeros)
+}
+
+/// Closed ellipsoid with semi-axes `a`, `b`, `c` along x, y, z and outward normals.
+#[cfg(test)]
+pub(crate) fn ellipsoid<F: IsFrameOfReference>(a: f32, b: f32, c: f32, n: usize) -> Mesh<F> {
+    use std::f32::consts::PI;
+    let point = |stack: usize, slice: usize| {
+        let (theta, phi) = (PI * stack as f32 / n as f32, 2.0 * PI * (slice % n) as f32 / n as f32);
+        // Snap the poles so every slice shares them
+        let phi = if stack == 0 || stack == n { 0.0 } else { phi };
+        na::Point3::new(a * theta.sin() * phi.cos(), b * theta.sin() * phi.sin(), c * theta.cos())
+    };
+    let mut triangles = Vec::new();
+    for stack in 0..n {
+        for slice in 0..n {
+            let (p00, p01) = (point(stack, slice), point(stack, slice + 1));
+            let (p10, p11) = (point(stack + 1, slice), point(stack + 1, slice + 1));
+            if stack != 0 {
+                triangles.push(Triangle { normal: None, vertices: [p00, p10, p01] });
+            }
+            if stack != n - 1 {
+                triangles.push(Triangle { normal: None, vertices: [p01, p10, p11] });
+            }
+        }
+    }
+    Mesh::from_triangles(triangles)
+}
@@ -0,0 +1,162 @@
+use nalgebra as na;
+
+use super::{Mesh, Registration};
+use crate::series::PoseSeries;
+use crate::transform::{gT, IsFrameOfReference, Mldivide, Transform};
+use crate::{Error, Model, Result};
+
+/// Closest approach between two bones in one frame, expressed in the frame of the second
+/// (e.g. tibial) bone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proximity {
+    /// Smallest signed distance between a vertex of either model and the surface of the other,
+    /// in mm; negative when the models overlap.
+    pub min_distance: f32,
+    /// Points on the first and the second model at `min_distance`, one of them a vertex.
+    pub closest: (na::Point3<f32>, na::Point3<f32>),
+    /// Points on the first and the second model closer than the contact distance, one pair per
+    /// vertex of either model.
+    pub pairs: Vec<(na::Point3<f32>, na::Point3<f32>)>,
+    /// Mean of the points on the second model in `pairs`, if there are any.
+    pub contact_centroid: Option<na::Point3<f32>>,
+}
+
+/// Proximity of two bones placed in global. The vertices of each model are measured against the
+/// surface of the other, so the distances do not depend on the argument order and penetration
+/// by either bone is found; the order only picks the frame the points are reported in. Fails on
+/// a mesh without faces or a registration that cannot be inverted.
+pub fn proximity<A: IsFrameOfReference, B: IsFrameOfReference>(
+    first: (&Mesh<Model<A>>, &Registration<A>, &gT<A>),
+    second: (&Mesh<Model<B>>, &Registration<B>, &gT<B>),
+    contact_distance: f32,
+) -> Result<Proximity> {
+    let (first_mesh, first_registration, first_pose) = first;
+    let (second_mesh, second_registration, second_pose) = second;
+    let b_from_a: Transform<B, A> = second_pose.rigid_inverse() * *first_pose;
+    let model_b_from_model_a = second_registration.transform.inverse()? * b_from_a * first_registration.transform;
+    let model_a_from_model_b = model_b_from_model_a.inverse()?;
+
+    // Pairs of points on the first and second model, both in the second model's frame
+    let mut closest = None;
+    let mut pairs = Vec::new();
+    let mut measure = |p: na::Point3<f32>, q: na::Point3<f32>, distance: f32| {
+        if closest.is_none_or(|(d, _, _)| distance < d) {
+            closest = Some((distance, p, q));
+        }
+        if distance < contact_distance {
+            pairs.push((p, q));
+        }
+    };
+    for vertex in first_mesh.vertices() {
+        let p = model_b_from_model_a.transform_point(vertex);
+        let (q, distance) = second_mesh.signed_closest_point(&p)?;
+        measure(p, q, distance);
+    }
+    for vertex in second_mesh.vertices() {
+        let (p, distance) = first_mesh.signed_closest_point(&model_a_from_model_b.transform_point(vertex))?;
+        measure(model_b_from_model_a.transform_point(&p), *vertex, distance);
+    }
+    // Report everything in the second bone's anatomical frame
+    let to_bone = |p: &na::Point3<f32>| second_registration.transform.transform_point(p);
+    let (min_distance, p, q) = closest.ok_or(Error::EmptyMesh)?;
+    let pairs: Vec<_> = pairs.iter().map(|(p, q)| (to_bone(p), to_bone(q))).collect();
+    let contact_centroid = (!pairs.is_empty()).then(|| {
+        na::Point3::from(pairs.iter().map(|(_, q)| q.coords).sum::<na::Vector3<f32>>() / pairs.len() as f32)
+    });
+    Ok(Proximity {
+        min_distance,
+        closest: (to_bone(&p), to_bone(&q)),
+        pairs,
+        contact_centroid,
+    })
+}
+
+/// [`proximity`] for every frame where both bones were tracked.
+pub fn contact_map<A: IsFrameOfReference, B: IsFrameOfReference>(
+    first: (&Mesh<Model<A>>, &Registration<A>, &PoseSeries<A>),
+    second: (&Mesh<Model<B>>, &Registration<B>, &PoseSeries<B>),
+    contact_distance: f32,
+) -> Result<Vec<Option<Proximity>>> {
+    if first.2.len() != second.2.len() {
+        return Err(Error::LengthMismatch { expected: first.2.len(), found: second.2.len() });
+    }
+    first
+        .2
+        .poses()
+        .iter()
+        .zip(second.2.poses())
+        .map(|(a, b)| match (a, b) {
+            (Some(a), Some(b)) => proximity((first.0, first.1, a), (second.0, second.1, b), contact_distance).map(Some),
+            _ => Ok(None),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bone_to_tracker::{Femur, Tibia};
+    use crate::mesh::ellipsoid;
+
+    fn registration<RB: IsFrameOfReference>(offset: na::Vector3<f32>) -> Registration<RB> {
+        Registration {
+            transform: Transform::from_parts(&offset, &na::UnitQuaternion::identity()),
+            rms: 0.0,
+            iterations: 0,
+        }
+    }
+
+    fn at<RB: IsFrameOfReference>(z: f32) -> gT<RB> {
+        Transform::from_parts(&na::Vector3::new(0.0, 0.0, z), &na::UnitQuaternion::identity())
+    }
+
+    #[test]
+    fn finds_gap_between_stacked_bones() {
+        // Two spheres of radius 10 stacked along z with a 2 mm gap
+        let femur = ellipsoid::<Model<Femur>>(10.0, 10.0, 10.0, 24);
+        let tibia = ellipsoid::<Model<Tibia>>(10.0, 10.0, 10.0, 24);
+        let (rf, rt) = (registration::<Femur>(na::Vector3::zeros()), registration::<Tibia>(na::Vector3::new(0.0, 0.0, 5.0)));
+        let result = proximity((&femur, &rf, &at(22.0)), (&tibia, &rt, &at(-5.0)), 3.0).unwrap();
+        assert_relative_eq!(result.min_distance, 2.0, epsilon = 1e-3);
+        // Closest point is the top of the tibial sphere, reported in the tibial frame
+        assert_relative_eq!(result.closest.1, na::Point3::new(0.0, 0.0, 15.0), epsilon = 1e-3);
+        assert!(!result.pairs.is_empty());
+        let centroid = result.contact_centroid.unwrap();
+        assert_relative_eq!(centroid.xy(), na::Point2::origin(), epsilon = 1e-2);
+    }
+
+    #[test]
+    fn finds_penetration_by_either_bone() {
+        // A small sphere pushed 1 mm into a face of a coarse one, away from its vertices
+        let coarse = ellipsoid::<Model<Femur>>(50.0, 50.0, 50.0, 4);
+        let small = ellipsoid::<Model<Tibia>>(2.0, 2.0, 2.0, 16);
+        let [a, b, c] = coarse.triangle(6);
+        let centroid = na::Point3::from((a.coords + b.coords + c.coords) / 3.0);
+        let inward = -coarse.normals()[6];
+        let centre = centroid.coords - inward * 1.0;
+        // Turn the small sphere so that a pole points into the face
+        let rotation = na::UnitQuaternion::rotation_between(&na::Vector3::z(), &inward).unwrap();
+        let small_pose = Transform::from_parts(&centre, &rotation);
+        let (rf, rt) = (registration::<Femur>(na::Vector3::zeros()), registration::<Tibia>(na::Vector3::zeros()));
+
+        let forward = proximity((&coarse, &rf, &at(0.0)), (&small, &rt, &small_pose), 0.5).unwrap();
+        let backward = proximity((&small, &rt, &small_pose), (&coarse, &rf, &at(0.0)), 0.5).unwrap();
+        assert_relative_eq!(forward.min_distance, -1.0, epsilon = 1e-2);
+        assert_relative_eq!(backward.min_distance, forward.min_distance, epsilon = 1e-5);
+        assert!(forward.contact_centroid.is_some() && backward.contact_centroid.is_some());
+    }
+
+    #[test]
+    fn maps_contact_over_a_trial() {
+        let femur = ellipsoid::<Model<Femur>>(10.0, 10.0, 10.0, 16);
+        let tibia = ellipsoid::<Model<Tibia>>(10.0, 10.0, 10.0, 16);
+        let (rf, rt) = (registration::<Femur>(na::Vector3::zeros()), registration::<Tibia>(na::Vector3::zeros()));
+        let femur_poses = PoseSeries::new(vec![0.0, 1.0, 2.0], vec![Some(at(25.0)), Some(at(19.0)), None]).unwrap();
+        let tibia_poses = PoseSeries::new(vec![0.0, 1.0, 2.0], vec![Some(at(0.0)); 3]).unwrap();
+        let map = contact_map((&femur, &rf, &femur_poses), (&tibia, &rt, &tibia_poses), 0.5).unwrap();
+        assert!(map[0].as_ref().unwrap().contact_centroid.is_none());
+        assert!(map[1].as_ref().unwrap().min_distance < 0.0);
+        assert!(map[1].as_ref().unwrap().contact_centroid.is_some());
+        assert!(map[2].is_none());
+    }
+}
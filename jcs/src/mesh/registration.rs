@@ -0,0 +1,238 @@
+use nalgebra as na;
+
+use super::Mesh;
+use crate::bone_to_tracker::DefinedTracker;
+use crate::data::ProbeData;
+use crate::transform::{IsFrameOfReference, Transform};
+use crate::{Error, Model, Result, RigidBody};
+
+/// Model to bone transform found by surface registration.
+#[derive(Debug)]
+pub struct Registration<RB: IsFrameOfReference> {
+    pub transform: Transform<RB, Model<RB>>,
+    /// RMS distance from the probed points to the model surface in mm.
+    pub rms: f32,
+    pub iterations: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IcpOptions {
+    pub max_iterations: usize,
+    /// Stop once the RMS improves by less than this (mm).
+    pub tolerance: f32,
+}
+
+impl Default for IcpOptions {
+    fn default() -> Self {
+        Self {
+            max_iterations: 100,
+            tolerance: 1e-4,
+        }
+    }
+}
+
+/// The landmarks' positions picked on the model, in the model frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelLandmarks {
+    pub medial: na::Point3<f32>,
+    pub lateral: na::Point3<f32>,
+    pub proximal_distal: na::Point3<f32>,
+}
+
+/// Least squares rigid transform taking `source` onto `target` (Kabsch).
+/// Fails on point sets of different lengths or with fewer than three points.
+pub fn fit_rigid<A: IsFrameOfReference, B: IsFrameOfReference>(
+    source: &[na::Point3<f32>],
+    target: &[na::Point3<f32>],
+) -> Result<Transform<A, B>> {
+    if source.len() != target.len() {
+        return Err(Error::LengthMismatch {
+            expected: source.len(),
+            found: target.len(),
+        });
+    }
+    if source.len() < 3 {
+        return Err(Error::DegenerateLandmarks(format!("a rigid fit to {} points", source.len())));
+    }
+    let centroid = |points: &[na::Point3<f32>]| {
+        points.iter().map(|p| p.coords).sum::<na::Vector3<f32>>() / points.len() as f32
+    };
+    let (cs, ct) = (centroid(source), centroid(target));
+    let covariance: na::Matrix3<f32> = source
+        .iter()
+        .zip(target)
+        .map(|(s, t)| (s.coords - cs) * (t.coords - ct).transpose())
+        .sum();
+    let svd = covariance.svd(true, true);
+    let (Some(u), Some(v_t)) = (svd.u, svd.v_t) else {
+        return Err(Error::DegenerateLandmarks("a rigid fit whose SVD failed".to_string()));
+    };
+    // Guard against reflections
+    let d = (v_t.transpose() * u.transpose()).determinant().signum();
+    let rotation = v_t.transpose() * na::Matrix3::from_diagonal(&na::Vector3::new(1.0, 1.0, d)) * u.transpose();
+    let translation = ct - rotation * cs;
+    let mut matrix = na::Matrix4::identity();
+    matrix.fixed_view_mut::<3, 3>(0, 0).copy_from(&rotation);
+    matrix.fixed_view_mut::<3, 1>(0, 3).copy_from(&translation);
+    Ok(Transform::new(na::Transform3::from_matrix_unchecked(matrix)))
+}
+
+/// Point-to-surface ICP of `points` (in the bone frame) onto `mesh`, starting from `initial`.
+/// Fails on a mesh without faces, fewer than three points or an `initial` transform that
+/// cannot be inverted.
+pub fn icp<RB: IsFrameOfReference>(
+    mesh: &Mesh<Model<RB>>,
+    points: &[na::Point3<f32>],
+    initial: Transform<RB, Model<RB>>,
+    options: &IcpOptions,
+) -> Result<Registration<RB>> {
+    if points.len() < 3 {
+        return Err(Error::DegenerateLandmarks(format!("registration to {} surface points", points.len())));
+    }
+    let mut model_from_bone = initial.inverse()?;
+    let mut rms = f32::INFINITY;
+    let mut iterations = 0;
+    while iterations < options.max_iterations {
+        iterations += 1;
+        let moved: Vec<_> = points.iter().map(|p| model_from_bone.transform_point(p)).collect();
+        let closest = closest_points(mesh, &moved)?;
+        let error = rms_distance(&moved, &closest);
+        let step: Transform<Model<RB>, Model<RB>> = fit_rigid(&moved, &closest)?;
+        model_from_bone = step * model_from_bone;
+        let converged = rms - error < options.tolerance;
+        rms = error;
+        if converged {
+            break;
+        }
+    }
+    let moved: Vec<_> = points.iter().map(|p| model_from_bone.transform_point(p)).collect();
+    let closest = closest_points(mesh, &moved)?;
+    Ok(Registration {
+        transform: model_from_bone.inverse()?,
+        rms: rms_distance(&moved, &closest),
+        iterations,
+    })
+}
+
+fn closest_points<F: IsFrameOfReference>(mesh: &Mesh<F>, points: &[na::Point3<f32>]) -> Result<Vec<na::Point3<f32>>> {
+    points.iter().map(|p| Ok(mesh.closest_point(p)?.0)).collect()
+}
+
+fn rms_distance(a: &[na::Point3<f32>], b: &[na::Point3<f32>]) -> f32 {
+    let sum: f32 = a.iter().zip(b).map(|(a, b)| (a - b).norm_squared()).sum();
+    (sum / a.len() as f32).sqrt()
+}
+
+impl<const ID: usize> RigidBody<ID>
+where
+    Self: DefinedTracker,
+{
+    /// Digitised landmarks expressed in the anatomical frame.
+    pub fn landmarks_in_bone(&self) -> [na::Point3<f32>; 3] {
+        let bone_from_global = self.in_global().rigid_inverse();
+        [self.medial.translations(), self.lateral.translations(), self.proximal_distal.translations()]
+            .map(|t| bone_from_global.transform_point(&na::Point3::from(*t)))
+    }
+    /// Initial model to bone transform from the landmarks picked on the model.
+    pub fn landmark_guess(&self, model: &ModelLandmarks) -> Result<Transform<Self, Model<Self>>> {
+        fit_rigid(&[model.medial, model.lateral, model.proximal_distal], &self.landmarks_in_bone())
+    }
+    /// Registers the attached model to surface points probed in global while the tracker
+    /// was in the same position as during landmark digitisation; `None` without a model.
+    pub fn register(
+        &self,
+        model: &ModelLandmarks,
+        surface: &[ProbeData],
+        options: &IcpOptions,
+    ) -> Result<Option<Registration<Self>>> {
+        let Some(mesh) = self.model() else { return Ok(None) };
+        let bone_from_global = self.in_global().rigid_inverse();
+        let points: Vec<_> = surface
+            .iter()
+            .map(|p| bone_from_global.transform_point(&na::Point3::from(*p.translation())))
+            .collect();
+        icp(mesh, &points, self.landmark_guess(model)?, options).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bone_to_tracker::{Femur, Side};
+    use crate::data::ProbeRawData;
+    use crate::mesh::ellipsoid;
+
+    fn truth() -> Transform<Femur, Model<Femur>> {
+        Transform::from_parts(
+            &na::Vector3::new(5.0, -12.0, 30.0),
+            &na::UnitQuaternion::from_euler_angles(0.3, -0.2, 1.1),
+        )
+    }
+
+    fn probe(p: na::Point3<f32>) -> ProbeData {
+        ProbeRawData::new("Black Probe", "Probe", 1.0, 0.0, 0.0, 0.0, p.x, p.y, p.z).into()
+    }
+
+    #[test]
+    fn fits_rigid_transform() {
+        let source = [na::Point3::new(0.0, 0.0, 0.0), na::Point3::new(10.0, 0.0, 0.0), na::Point3::new(0.0, 5.0, 2.0)];
+        let target: Vec<_> = source.iter().map(|p| truth().transform_point(p)).collect();
+        let fit: Transform<Femur, Model<Femur>> = fit_rigid(&source, &target).unwrap();
+        assert_relative_eq!(fit.inner(), truth().inner(), epsilon = 1e-3);
+    }
+
+    #[test]
+    fn rejects_mismatched_or_too_few_points() {
+        let source = [na::Point3::new(0.0, 0.0, 0.0), na::Point3::new(10.0, 0.0, 0.0), na::Point3::new(0.0, 5.0, 2.0)];
+        assert!(matches!(
+            fit_rigid::<Femur, Model<Femur>>(&source, &source[..2]),
+            Err(Error::LengthMismatch { expected: 3, found: 2 })
+        ));
+        assert!(matches!(fit_rigid::<Femur, Model<Femur>>(&[], &[]), Err(Error::DegenerateLandmarks(_))));
+        let mesh = ellipsoid::<Model<Femur>>(40.0, 25.0, 15.0, 16);
+        assert!(matches!(icp(&mesh, &[], truth(), &IcpOptions::default()), Err(Error::DegenerateLandmarks(_))));
+    }
+
+    #[test]
+    fn icp_recovers_perturbed_pose() {
+        let mesh = ellipsoid::<Model<Femur>>(40.0, 25.0, 15.0, 16);
+        let points: Vec<_> = mesh.vertices().iter().step_by(7).map(|p| truth().transform_point(p)).collect();
+        let nudge = Transform::<Femur, Femur>::from_parts(
+            &na::Vector3::new(2.0, -1.5, 1.0),
+            &na::UnitQuaternion::from_euler_angles(0.05, 0.04, -0.06),
+        );
+        let registration = icp(&mesh, &points, nudge * truth(), &IcpOptions::default()).unwrap();
+        assert!(registration.rms < 0.05, "rms {}", registration.rms);
+        assert_relative_eq!(registration.transform.inner(), truth().inner(), epsilon = 5e-2);
+    }
+
+    #[test]
+    fn registers_from_landmarks_and_surface() {
+        let mesh = ellipsoid::<Model<Femur>>(40.0, 25.0, 15.0, 16);
+        let model = ModelLandmarks {
+            medial: na::Point3::new(0.0, 25.0, 0.0),
+            lateral: na::Point3::new(0.0, -25.0, 0.0),
+            proximal_distal: na::Point3::new(40.0, 0.0, 0.0),
+        };
+        // Place the bone in global and digitise the landmarks and surface there
+        let global_from_model = |p: &na::Point3<f32>| na::Point3::new(p.z + 100.0, p.x, p.y - 2000.0);
+        let tracker = ProbeRawData::new("Y", "Y", 0.9, 0.1, -0.2, 0.3, -150.0, -20.0, -2100.0);
+        let femur = Femur::new(
+            Side::Right,
+            probe(global_from_model(&model.medial)),
+            probe(global_from_model(&model.lateral)),
+            probe(global_from_model(&model.proximal_distal)),
+            tracker.into(),
+        ).unwrap()
+        .with_model(mesh.clone());
+        let surface: Vec<_> = mesh.vertices().iter().step_by(5).map(|p| probe(global_from_model(p))).collect();
+
+        let registration = femur.register(&model, &surface, &IcpOptions::default()).unwrap().unwrap();
+        assert!(registration.rms < 0.05, "rms {}", registration.rms);
+        let bone = femur.landmarks_in_bone();
+        let model_points = [model.medial, model.lateral, model.proximal_distal];
+        for (b, m) in bone.iter().zip(model_points) {
+            assert_relative_eq!(*b, registration.transform.transform_point(&m), epsilon = 1e-2);
+        }
+    }
+}
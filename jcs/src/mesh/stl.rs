@@ -0,0 +1,162 @@
+use nalgebra as na;
+
+const HEADER: usize = 80;
+const RECORD: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Triangle {
+    /// Normal stored in the file; `None` when it was left as zero.
+    pub normal: Option<na::Vector3<f32>>,
+    pub vertices: [na::Point3<f32>; 3],
+}
+
+use crate::{Error, Result};
+
+fn invalid(msg: String) -> Error {
+    Error::Format(msg)
+}
+
+pub(crate) fn parse(bytes: &[u8]) -> Result<Vec<Triangle>> {
+    if bytes.is_empty() {
+        return Err(invalid("empty STL file".to_string()));
+    }
+    // Binary files may also start with "solid", so trust the size check first
+    if let Some(count) = bytes.get(HEADER..HEADER + 4) {
+        let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+        if bytes.len() == HEADER + 4 + count * RECORD {
+            return Ok(parse_binary(&bytes[HEADER + 4..]));
+        }
+    }
+    let text = std::str::from_utf8(bytes).map_err(|_| invalid("STL is neither binary nor ASCII".to_string()))?;
+    parse_ascii(text)
+}
+
+fn normal(n: na::Vector3<f32>) -> Option<na::Vector3<f32>> {
+    n.try_normalize(f32::EPSILON)
+}
+
+fn parse_binary(records: &[u8]) -> Vec<Triangle> {
+    records
+        .chunks_exact(RECORD)
+        .map(|r| {
+            let f = |i: usize| f32::from_le_bytes(r[4 * i..4 * i + 4].try_into().unwrap());
+            let v = |i: usize| na::Point3::new(f(i), f(i + 1), f(i + 2));
+            Triangle {
+                normal: normal(na::Vector3::new(f(0), f(1), f(2))),
+                vertices: [v(3), v(6), v(9)],
+            }
+        })
+        .collect()
+}
+
+fn parse_ascii(text: &str) -> Result<Vec<Triangle>> {
+    let mut triangles = Vec::new();
+    let mut normal_ = None;
+    let mut vertices = Vec::with_capacity(3);
+    for (n, line) in text.lines().enumerate() {
+        let mut words = line.split_whitespace();
+        let numbers = |words: std::str::SplitWhitespace| -> Result<na::Vector3<f32>> {
+            let values: Vec<f32> = words
+                .map(str::parse)
+                .collect::<std::result::Result<_, _>>()
+                .map_err(|_| invalid(format!("line {}: invalid number", n + 1)))?;
+            match values[..] {
+                [x, y, z] => Ok(na::Vector3::new(x, y, z)),
+                _ => Err(invalid(format!("line {}: expected three coordinates", n + 1))),
+            }
+        };
+        match words.next() {
+            Some("facet") => {
+                words.next(); // "normal"
+                normal_ = normal(numbers(words)?);
+                vertices.clear();
+            }
+            Some("vertex") => vertices.push(numbers(words)?.into()),
+            Some("endfacet") => {
+                let vertices: [na::Point3<f32>; 3] = vertices
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| invalid(format!("line {}: facet without three vertices", n + 1)))?;
+                triangles.push(Triangle { normal: normal_, vertices });
+            }
+            _ => {}
+        }
+    }
+    if triangles.is_empty() {
+        return Err(invalid("no facets in STL file".to_string()));
+    }
+    Ok(triangles)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mesh::Mesh;
+    use crate::FemurModel;
+
+    use super::*;
+
+    const TETRAHEDRON: &str = "solid tet
+facet normal 0 0 -1
+  outer loop
+    vertex 0 0 0
+    vertex 0 1 0
+    vertex 1 0 0
+  endloop
+endfacet
+facet normal 0 -1 0
+  outer loop
+    vertex 0 0 0
+    vertex 1 0 0
+    vertex 0 0 1
+  endloop
+endfacet
+facet normal -1 0 0
+  outer loop
+    vertex 0 0 0
+    vertex 0 0 1
+    vertex 0 1 0
+  endloop
+endfacet
+facet normal 0 0 0
+  outer loop
+    vertex 1 0 0
+    vertex 0 1 0
+    vertex 0 0 1
+  endloop
+endfacet
+endsolid tet
+";
+
+    #[test]
+    fn reads_ascii_and_merges_vertices() {
+        let mesh = Mesh::<FemurModel>::from_stl(TETRAHEDRON.as_bytes()).unwrap();
+        assert_eq!(mesh.vertices().len(), 4);
+        assert_eq!(mesh.faces().len(), 4);
+        assert_relative_eq!(mesh.normals()[0], na::Vector3::new(0.0, 0.0, -1.0));
+        // Zero normals are recomputed from the winding
+        let expected = na::Vector3::new(1.0, 1.0, 1.0).normalize();
+        assert_relative_eq!(mesh.normals()[3], expected, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn reads_binary() {
+        let ascii = parse(TETRAHEDRON.as_bytes()).unwrap();
+        let mut bytes = b"solid but actually binary".to_vec();
+        bytes.resize(HEADER, 0);
+        bytes.extend_from_slice(&(ascii.len() as u32).to_le_bytes());
+        for t in &ascii {
+            let n = t.normal.unwrap_or_else(na::Vector3::zeros);
+            for v in std::iter::once(n).chain(t.vertices.iter().map(|v| v.coords)) {
+                v.iter().for_each(|c| bytes.extend_from_slice(&c.to_le_bytes()));
+            }
+            bytes.extend_from_slice(&[0, 0]);
+        }
+        assert_eq!(parse(&bytes).unwrap(), ascii);
+    }
+
+    #[test]
+    fn rejects_empty_models() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/models/femur.stl");
+        assert!(Mesh::<FemurModel>::load(path).is_err());
+    }
+}
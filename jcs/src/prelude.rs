@@ -1,2 +1,6 @@
 pub use crate::transform::Transform;
-pub use crate::bone_to_tracker::Kinematics;
+pub use crate::bone_to_tracker::{Kinematics, Mirror, Motion, Side, VirtualLandmark};
+#[cfg(feature = "knee")]
+pub use crate::bone_to_tracker::{Femur, FemurModel, Patella, PatellaModel, Tibia, TibiaModel};
+pub use crate::data::{Datum, QualityThresholds};
+pub use crate::solvers::{GroodAndSuntay, Solver};
@@ -0,0 +1,280 @@
+use nalgebra as na;
+
+use super::{runs, PoseSeries};
+use crate::bone_to_tracker::{Kinematics, Motion};
+use crate::transform::IsFrameOfReference;
+use crate::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Differentiation {
+    /// Central differences, one-sided at the ends of each run.
+    FiniteDifference,
+    /// Derivative of a natural cubic spline through the samples.
+    Spline,
+}
+
+/// Derivative of `values` sampled at `time`, which may be unevenly spaced. Fails unless there is
+/// one time per value.
+pub fn differentiate(time: &[f64], values: &[f64], method: Differentiation) -> Result<Vec<f64>> {
+    check_lengths(time.len(), values.len())?;
+    Ok(derivative(time, values, method))
+}
+
+fn check_lengths(expected: usize, found: usize) -> Result<()> {
+    if expected != found {
+        return Err(Error::LengthMismatch { expected, found });
+    }
+    Ok(())
+}
+
+fn derivative(time: &[f64], values: &[f64], method: Differentiation) -> Vec<f64> {
+    let n = values.len();
+    if n < 2 {
+        return vec![0.0; n];
+    }
+    match method {
+        Differentiation::FiniteDifference => (0..n)
+            .map(|i| {
+                let (a, b) = (i.saturating_sub(1), (i + 1).min(n - 1));
+                (values[b] - values[a]) / (time[b] - time[a])
+            })
+            .collect(),
+        Differentiation::Spline => {
+            let m = spline_second_derivatives(time, values);
+            let h = |i: usize| time[i + 1] - time[i];
+            let mut derivative: Vec<f64> = (0..n - 1)
+                .map(|i| (values[i + 1] - values[i]) / h(i) - h(i) * (2.0 * m[i] + m[i + 1]) / 6.0)
+                .collect();
+            let last = n - 2;
+            derivative.push((values[n - 1] - values[last]) / h(last) + h(last) * (m[last] + 2.0 * m[n - 1]) / 6.0);
+            derivative
+        }
+    }
+}
+
+// Second derivatives at the knots of a natural cubic spline (Thomas algorithm)
+fn spline_second_derivatives(time: &[f64], values: &[f64]) -> Vec<f64> {
+    let n = values.len();
+    let mut m = vec![0.0; n];
+    if n < 3 {
+        return m;
+    }
+    let h: Vec<f64> = time.windows(2).map(|w| w[1] - w[0]).collect();
+    let mut diagonal = vec![0.0; n];
+    let mut rhs = vec![0.0; n];
+    for i in 1..n - 1 {
+        diagonal[i] = 2.0 * (h[i - 1] + h[i]);
+        rhs[i] = 6.0 * ((values[i + 1] - values[i]) / h[i] - (values[i] - values[i - 1]) / h[i - 1]);
+    }
+    for i in 2..n - 1 {
+        let w = h[i - 1] / diagonal[i - 1];
+        diagonal[i] -= w * h[i - 1];
+        rhs[i] -= w * rhs[i - 1];
+    }
+    for i in (1..n - 1).rev() {
+        m[i] = (rhs[i] - h[i] * m[i + 1]) / diagonal[i];
+    }
+    m
+}
+
+// Differentiates each component of vector samples, run by run
+fn differentiate_vectors<const D: usize>(
+    time: &[f64],
+    values: &[Option<na::SVector<f64, D>>],
+    method: Differentiation,
+) -> Vec<Option<na::SVector<f64, D>>> {
+    let mut derivatives = vec![None; values.len()];
+    for run in runs(values).into_iter().filter(|r| r.len() > 1) {
+        let columns: Vec<Vec<f64>> = (0..D)
+            .map(|c| {
+                let column: Vec<f64> = values[run.clone()].iter().map(|v| v.unwrap()[c]).collect();
+                derivative(&time[run.clone()], &column, method)
+            })
+            .collect();
+        for (n, i) in run.enumerate() {
+            derivatives[i] = Some(na::SVector::from_fn(|c, _| columns[c][n]));
+        }
+    }
+    derivatives
+}
+
+// ω = 2 q̇ q*, with quaternions aligned to one hemisphere so the components are continuous
+fn angular_velocity(time: &[f64], rotations: &[Option<na::UnitQuaternion<f64>>], method: Differentiation) -> Vec<Option<na::Vector3<f64>>> {
+    let mut previous: Option<na::Vector4<f64>> = None;
+    let coords: Vec<Option<na::Vector4<f64>>> = rotations
+        .iter()
+        .map(|q| {
+            let mut q = q.as_ref()?.into_inner().coords;
+            if previous.is_some_and(|p| p.dot(&q) < 0.0) {
+                q = -q;
+            }
+            previous = Some(q);
+            Some(q)
+        })
+        .collect();
+    differentiate_vectors(time, &coords, method)
+        .into_iter()
+        .zip(&coords)
+        .map(|(dq, q)| {
+            let (dq, q) = (na::Quaternion::from(dq?), na::Quaternion::from(q.unwrap()));
+            Some((dq * q.conjugate()).imag() * 2.0)
+        })
+        .collect()
+}
+
+fn to_f32(values: Vec<Option<na::Vector3<f64>>>) -> Vec<Option<na::Vector3<f32>>> {
+    values.into_iter().map(|v| v.map(|v| v.cast())).collect()
+}
+
+impl<F: IsFrameOfReference> PoseSeries<F> {
+    fn translations(&self) -> Vec<Option<na::Vector3<f64>>> {
+        self.poses.iter().map(|p| p.map(|p| p.translation().coords.cast())).collect()
+    }
+    fn rotations(&self) -> Vec<Option<na::UnitQuaternion<f64>>> {
+        self.poses.iter().map(|p| p.map(|p| p.rotation().cast())).collect()
+    }
+
+    /// Velocity of the origin in global, in mm/s.
+    pub fn linear_velocity(&self, method: Differentiation) -> Vec<Option<na::Vector3<f32>>> {
+        to_f32(differentiate_vectors(&self.time, &self.translations(), method))
+    }
+    pub fn linear_acceleration(&self, method: Differentiation) -> Vec<Option<na::Vector3<f32>>> {
+        let velocity = differentiate_vectors(&self.time, &self.translations(), method);
+        to_f32(differentiate_vectors(&self.time, &velocity, method))
+    }
+    /// Angular velocity in global, in rad/s.
+    pub fn angular_velocity(&self, method: Differentiation) -> Vec<Option<na::Vector3<f32>>> {
+        to_f32(angular_velocity(&self.time, &self.rotations(), method))
+    }
+    pub fn angular_acceleration(&self, method: Differentiation) -> Vec<Option<na::Vector3<f32>>> {
+        let velocity = angular_velocity(&self.time, &self.rotations(), method);
+        to_f32(differentiate_vectors(&self.time, &velocity, method))
+    }
+
+    fn rotations_relative_to<G: IsFrameOfReference>(&self, reference: &PoseSeries<G>) -> Result<Vec<Option<na::UnitQuaternion<f64>>>> {
+        check_lengths(self.len(), reference.len())?;
+        Ok(self
+            .rotations()
+            .into_iter()
+            .zip(reference.rotations())
+            .map(|(q, r)| Some(r?.inverse() * q?))
+            .collect())
+    }
+    /// Angular velocity of this frame relative to `reference`, expressed in `reference`, in rad/s.
+    /// For the tibia series with the femur as reference this is the tibiofemoral angular velocity.
+    /// Fails if the series differ in length.
+    pub fn angular_velocity_relative_to<G: IsFrameOfReference>(
+        &self,
+        reference: &PoseSeries<G>,
+        method: Differentiation,
+    ) -> Result<Vec<Option<na::Vector3<f32>>>> {
+        Ok(to_f32(angular_velocity(&self.time, &self.rotations_relative_to(reference)?, method)))
+    }
+    pub fn angular_acceleration_relative_to<G: IsFrameOfReference>(
+        &self,
+        reference: &PoseSeries<G>,
+        method: Differentiation,
+    ) -> Result<Vec<Option<na::Vector3<f32>>>> {
+        let velocity = angular_velocity(&self.time, &self.rotations_relative_to(reference)?, method);
+        Ok(to_f32(differentiate_vectors(&self.time, &velocity, method)))
+    }
+}
+
+impl Kinematics {
+    /// Rate of change of every degree of freedom (deg/s and mm/s), sampled at `time`. Fails
+    /// unless there is one time per frame.
+    pub fn derivative(&self, time: &[f64], method: Differentiation) -> Result<Kinematics> {
+        check_lengths(self.len(), time.len())?;
+        let dofs: Vec<Option<na::SVector<f64, 6>>> = self
+            .frames()
+            .iter()
+            .map(|m| m.map(|m| na::SVector::from(m.dofs()).cast()))
+            .collect();
+        Ok(differentiate_vectors(time, &dofs, method)
+            .into_iter()
+            .map(|d| d.map(|d| Motion::from_dofs(d.cast::<f32>().into())))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bone_to_tracker::{Femur, Tibia};
+    use crate::transform::Transform;
+    use crate::Tracker;
+
+    const FS: f64 = 100.0;
+    const METHODS: [Differentiation; 2] = [Differentiation::FiniteDifference, Differentiation::Spline];
+
+    fn spinning<F: IsFrameOfReference>(rate: f32, n: usize) -> PoseSeries<F> {
+        let time = (0..n).map(|i| i as f64 / FS).collect();
+        let poses = (0..n)
+            .map(|i| {
+                let t = i as f32 / FS as f32;
+                let rotation = na::UnitQuaternion::from_axis_angle(&na::Vector3::z_axis(), rate * t);
+                let translation = na::Vector3::new(100.0 * t * t, 5.0 * t, 0.0);
+                Some(Transform::from_parts(&translation, &rotation))
+            })
+            .collect();
+        PoseSeries::new(time, poses).unwrap()
+    }
+
+    #[test]
+    fn derivative_of_polynomials() {
+        let time: Vec<f64> = (0..50).map(|i| i as f64 * 0.02).collect();
+        let values: Vec<f64> = time.iter().map(|t| 3.0 * t * t).collect();
+        for method in METHODS {
+            let derivative = differentiate(&time, &values, method).unwrap();
+            for (t, d) in time.iter().zip(&derivative).skip(5).take(40) {
+                assert_relative_eq!(*d, 6.0 * t, epsilon = 1e-2);
+            }
+        }
+    }
+
+    #[test]
+    fn linear_velocity_and_acceleration() {
+        let series: PoseSeries<Tracker<Tibia>> = spinning(1.0, 100);
+        for method in METHODS {
+            let velocity = series.linear_velocity(method);
+            let acceleration = series.linear_acceleration(method);
+            for i in 10..90 {
+                let t = i as f32 / FS as f32;
+                assert_relative_eq!(velocity[i].unwrap(), na::Vector3::new(200.0 * t, 5.0, 0.0), epsilon = 1e-1);
+                assert_relative_eq!(acceleration[i].unwrap(), na::Vector3::new(200.0, 0.0, 0.0), epsilon = 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn angular_velocity_relative_to_reference() {
+        let femur: PoseSeries<Femur> = spinning(0.5, 100);
+        let tibia: PoseSeries<Tibia> = spinning(2.0, 100);
+        for method in METHODS {
+            let global = tibia.angular_velocity(method);
+            let relative = tibia.angular_velocity_relative_to(&femur, method).unwrap();
+            let acceleration = tibia.angular_acceleration_relative_to(&femur, method).unwrap();
+            for i in 5..95 {
+                assert_relative_eq!(global[i].unwrap(), na::Vector3::new(0.0, 0.0, 2.0), epsilon = 1e-2);
+                assert_relative_eq!(relative[i].unwrap(), na::Vector3::new(0.0, 0.0, 1.5), epsilon = 1e-2);
+                assert_relative_eq!(acceleration[i].unwrap(), na::Vector3::zeros(), epsilon = 1e-1);
+            }
+        }
+    }
+
+    #[test]
+    fn kinematics_rates_skip_gaps() {
+        let time: Vec<f64> = (0..30).map(|i| i as f64 / FS).collect();
+        let kinematics: Kinematics = time
+            .iter()
+            .enumerate()
+            .map(|(i, t)| (i != 12).then(|| Motion::from_dofs([30.0 * *t as f32, 0.0, 0.0, 2.0 * *t as f32, 0.0, 0.0])))
+            .collect();
+        let rates = kinematics.derivative(&time, Differentiation::FiniteDifference).unwrap();
+        assert!(rates.frames()[12].is_none());
+        assert_relative_eq!(rates.frames()[5].unwrap().flexion(), 30.0, epsilon = 1e-3);
+        assert_relative_eq!(rates.frames()[20].unwrap().anterior(), 2.0, epsilon = 1e-3);
+        let error = kinematics.derivative(&time[1..], Differentiation::FiniteDifference);
+        assert!(matches!(error, Err(Error::LengthMismatch { expected: 30, found: 29 })));
+    }
+}
@@ -0,0 +1,259 @@
+use std::f64::consts::{PI, SQRT_2};
+
+use nalgebra as na;
+
+use super::{runs, PoseSeries};
+use crate::bone_to_tracker::{Kinematics, Motion};
+use crate::transform::{IsFrameOfReference, Transform};
+use crate::{Error, Result};
+
+// Samples reflected at each end of a run to settle the filter before the data starts
+const PADDING: usize = 30;
+
+/// Second order low-pass Butterworth applied forward and backward (zero lag).
+/// The cutoff is corrected for the double pass so the result is -3 dB at `cutoff`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Butterworth {
+    cutoff: f64,
+    sample_rate: f64,
+    b: [f64; 3],
+    a: [f64; 2],
+}
+
+impl Butterworth {
+    /// Fails unless the cutoff lies between 0 and the Nyquist frequency.
+    pub fn new(cutoff: f64, sample_rate: f64) -> Result<Self> {
+        if !(cutoff > 0.0 && cutoff < sample_rate / 2.0) {
+            return Err(Error::InvalidCutoff { cutoff, sample_rate });
+        }
+        // Winter's correction for a second order filter applied twice
+        let correction = (2f64.powf(0.5) - 1.0).powf(0.25);
+        let k = (PI * cutoff / sample_rate).tan() / correction;
+        let norm = 1.0 / (1.0 + SQRT_2 * k + k * k);
+        let b0 = k * k * norm;
+        Ok(Self {
+            cutoff,
+            sample_rate,
+            b: [b0, 2.0 * b0, b0],
+            a: [2.0 * (k * k - 1.0) * norm, (1.0 - SQRT_2 * k + k * k) * norm],
+        })
+    }
+    /// Filter with the cutoff chosen by [`residual_analysis`] on `signal`.
+    pub fn from_residuals(signal: &[f64], sample_rate: f64) -> Result<Self> {
+        Self::new(residual_analysis(signal, sample_rate)?, sample_rate)
+    }
+    pub fn cutoff(&self) -> f64 {
+        self.cutoff
+    }
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    pub fn filtfilt(&self, signal: &[f64]) -> Vec<f64> {
+        if signal.len() < 2 {
+            return signal.to_vec();
+        }
+        let pad = PADDING.min(signal.len() - 1);
+        let (first, last) = (signal[0], signal[signal.len() - 1]);
+        // Odd reflection keeps the value and slope continuous at the ends
+        let mut x: Vec<f64> = (1..=pad).rev().map(|i| 2.0 * first - signal[i]).collect();
+        x.extend_from_slice(signal);
+        x.extend((1..=pad).map(|i| 2.0 * last - signal[signal.len() - 1 - i]));
+
+        let mut y = self.pass(&x);
+        y.reverse();
+        let mut y = self.pass(&y);
+        y.reverse();
+        y[pad..pad + signal.len()].to_vec()
+    }
+
+    fn pass(&self, x: &[f64]) -> Vec<f64> {
+        let [b0, b1, b2] = self.b;
+        let [a1, a2] = self.a;
+        // Start from the steady state of the first sample
+        let (mut x1, mut x2, mut y1, mut y2) = (x[0], x[0], x[0], x[0]);
+        x.iter()
+            .map(|&x0| {
+                let y0 = b0 * x0 + b1 * x1 + b2 * x2 - a1 * y1 - a2 * y2;
+                (x2, x1, y2, y1) = (x1, x0, y1, y0);
+                y0
+            })
+            .collect()
+    }
+}
+
+/// Winter's residual analysis: the cutoff where the residual between raw and filtered signal
+/// drops to the level explained by noise alone, estimated by extrapolating the linear
+/// high-frequency part of the residual curve back to 0 Hz.
+pub fn residual_analysis(signal: &[f64], sample_rate: f64) -> Result<f64> {
+    let nyquist = sample_rate / 2.0;
+    let step = nyquist / 100.0;
+    let cutoffs: Vec<f64> = (1..95).map(|i| i as f64 * step).collect();
+    let residuals: Vec<f64> = cutoffs
+        .iter()
+        .map(|&fc| {
+            let filtered = Butterworth::new(fc, sample_rate)?.filtfilt(signal);
+            let sum: f64 = signal.iter().zip(&filtered).map(|(x, y)| (x - y).powi(2)).sum();
+            Ok((sum / signal.len() as f64).sqrt())
+        })
+        .collect::<Result<_>>()?;
+
+    // Noise is assumed to dominate the upper half of the spectrum
+    let tail = cutoffs.len() / 2;
+    let (xs, ys) = (&cutoffs[tail..], &residuals[tail..]);
+    let n = xs.len() as f64;
+    let (mx, my) = (xs.iter().sum::<f64>() / n, ys.iter().sum::<f64>() / n);
+    let slope = xs.iter().zip(ys).map(|(x, y)| (x - mx) * (y - my)).sum::<f64>()
+        / xs.iter().map(|x| (x - mx).powi(2)).sum::<f64>();
+    let intercept = my - slope * mx;
+
+    Ok(cutoffs
+        .iter()
+        .zip(&residuals)
+        .find(|(_, r)| **r <= intercept)
+        .map(|(fc, _)| *fc)
+        .unwrap_or(cutoffs[tail]))
+}
+
+impl Kinematics {
+    /// Filters each degree of freedom, treating every run between gaps separately.
+    pub fn filtered(&self, filter: &Butterworth) -> Self {
+        let frames = self.frames();
+        let mut filtered = frames.to_vec();
+        for run in runs(frames) {
+            let channels: Vec<Vec<f64>> = (0..6)
+                .map(|dof| {
+                    let channel: Vec<f64> = frames[run.clone()].iter().map(|m| m.unwrap().dofs()[dof] as f64).collect();
+                    filter.filtfilt(&channel)
+                })
+                .collect();
+            for (n, i) in run.enumerate() {
+                let dofs = std::array::from_fn(|dof| channels[dof][n] as f32);
+                filtered[i] = Some(Motion::from_dofs(dofs));
+            }
+        }
+        filtered.into_iter().collect()
+    }
+}
+
+impl<F: IsFrameOfReference> PoseSeries<F> {
+    /// Filters translations directly and rotations as hemisphere-aligned quaternion components,
+    /// renormalised afterwards. Each run between gaps is filtered separately.
+    pub fn filtered(&self, filter: &Butterworth) -> Self {
+        let mut poses = self.poses.clone();
+        for run in runs(&self.poses) {
+            let mut previous: Option<na::Vector4<f32>> = None;
+            let samples: Vec<[f64; 7]> = self.poses[run.clone()]
+                .iter()
+                .map(|pose| {
+                    let pose = pose.unwrap();
+                    let t = pose.translation();
+                    let mut q = pose.rotation().into_inner().coords;
+                    if previous.is_some_and(|p| p.dot(&q) < 0.0) {
+                        q = -q;
+                    }
+                    previous = Some(q);
+                    [t.x, t.y, t.z, q.x, q.y, q.z, q.w].map(f64::from)
+                })
+                .collect();
+            let channels: Vec<Vec<f64>> = (0..7)
+                .map(|c| filter.filtfilt(&samples.iter().map(|s| s[c]).collect::<Vec<_>>()))
+                .collect();
+            for (n, i) in run.enumerate() {
+                let c = |k: usize| channels[k][n] as f32;
+                let translation = na::Vector3::new(c(0), c(1), c(2));
+                let rotation = na::UnitQuaternion::new_normalize(na::Quaternion::new(c(6), c(3), c(4), c(5)));
+                poses[i] = Some(Transform::from_parts(&translation, &rotation));
+            }
+        }
+        Self {
+            time: self.time.clone(),
+            poses,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bone_to_tracker::Tibia;
+    use crate::Tracker;
+
+    const FS: f64 = 60.0;
+
+    fn sine(freq: f64, n: usize) -> Vec<f64> {
+        (0..n).map(|i| (2.0 * PI * freq * i as f64 / FS).sin()).collect()
+    }
+
+    // Deterministic noise in [-0.5, 0.5)
+    fn noise(n: usize) -> Vec<f64> {
+        let mut state: u64 = 0x2545F4914F6CDD1D;
+        (0..n)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                (state >> 11) as f64 / (1u64 << 53) as f64 - 0.5
+            })
+            .collect()
+    }
+
+    #[test]
+    fn passes_slow_signals_without_lag() {
+        let filter = Butterworth::new(6.0, FS).unwrap();
+        let signal = sine(1.0, 240);
+        let filtered = filter.filtfilt(&signal);
+        for (x, y) in signal.iter().zip(&filtered).skip(10).take(220) {
+            assert_relative_eq!(x, y, epsilon = 1e-2);
+        }
+        assert!(filter.filtfilt(&[3.0; 50]).iter().all(|y| (y - 3.0).abs() < 1e-9));
+    }
+
+    #[test]
+    fn attenuates_fast_signals() {
+        let filtered = Butterworth::new(4.0, FS).unwrap().filtfilt(&sine(20.0, 240));
+        assert!(filtered.iter().skip(30).take(180).all(|y| y.abs() < 0.05));
+    }
+
+    #[test]
+    fn residual_analysis_finds_signal_band() {
+        let signal: Vec<f64> = sine(1.5, 600).iter().zip(noise(600)).map(|(s, n)| 10.0 * s + 0.5 * n).collect();
+        let cutoff = residual_analysis(&signal, FS).unwrap();
+        assert!(cutoff > 1.5 && cutoff < 12.0, "cutoff {cutoff}");
+    }
+
+    #[test]
+    fn filters_around_gaps() {
+        let kinematics: Kinematics = (0..40)
+            .map(|i| (i != 20).then(|| Motion::from_dofs([i as f32, 0.0, 1.0, 2.0, 3.0, 4.0])))
+            .collect();
+        let filtered = kinematics.filtered(&Butterworth::new(6.0, FS).unwrap());
+        assert!(filtered.frames()[20].is_none());
+        assert_relative_eq!(filtered.frames()[10].unwrap().flexion(), 10.0, epsilon = 1e-3);
+        assert_relative_eq!(filtered.frames()[30].unwrap().varus(), 1.0, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn filters_rotations_across_quaternion_sign_flips() {
+        let n = 60;
+        let time = (0..n).map(|i| i as f64 / FS).collect();
+        let poses = (0..n)
+            .map(|i| {
+                let q = na::UnitQuaternion::from_axis_angle(&na::Vector3::y_axis(), 0.5 + 0.01 * i as f32);
+                // Alternate between the two equivalent quaternions
+                let q = if i % 2 == 0 { q } else { na::UnitQuaternion::new_unchecked(-q.into_inner()) };
+                Some(Transform::from_parts(&na::Vector3::new(1.0, 2.0, 3.0), &q))
+            })
+            .collect();
+        let series = PoseSeries::<Tracker<Tibia>>::new(time, poses).unwrap();
+        let filtered = series.filtered(&Butterworth::new(6.0, FS).unwrap());
+        for (a, b) in series.poses().iter().zip(filtered.poses()) {
+            assert!(a.unwrap().rotation().angle_to(&b.unwrap().rotation()) < 1e-3);
+        }
+    }
+
+    #[test]
+    fn rejects_cutoffs_above_nyquist() {
+        assert!(matches!(Butterworth::new(30.0, FS), Err(Error::InvalidCutoff { .. })));
+        assert!(matches!(Butterworth::new(0.0, FS), Err(Error::InvalidCutoff { .. })));
+        assert!(Butterworth::new(6.0, f64::NAN).is_err());
+    }
+}
@@ -0,0 +1,214 @@
+use nalgebra as na;
+
+use super::PoseSeries;
+use crate::transform::{gT, IsFrameOfReference, Mldivide, Transform};
+use crate::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Linear translation and spherical rotation between the samples bounding the gap.
+    Slerp,
+    /// Cubic Hermite spline with tangents taken from the samples either side of the gap.
+    Spline,
+}
+
+/// Run of `len` missing samples starting at index `start`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gap {
+    pub start: usize,
+    pub len: usize,
+}
+
+impl Gap {
+    pub fn end(&self) -> usize {
+        self.start + self.len
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FillReport {
+    pub filled: Vec<Gap>,
+    /// Gaps left missing: too long, at either end of the series or without reference data.
+    pub unfilled: Vec<Gap>,
+}
+
+impl<F: IsFrameOfReference> PoseSeries<F> {
+    pub fn fill_gaps(&mut self, method: Interpolation, max_gap: usize) -> FillReport {
+        let mut report = FillReport::default();
+        for gap in self.gaps() {
+            if gap.len > max_gap || gap.start == 0 || gap.end() == self.len() {
+                report.unfilled.push(gap);
+                continue;
+            }
+            let (a, b) = (gap.start - 1, gap.end());
+            for i in gap.start..b {
+                let t = self.fraction(a, b, i);
+                let pose = match method {
+                    Interpolation::Slerp => self.pose(a).interpolate(&self.pose(b), t),
+                    Interpolation::Spline => self.hermite(a, b, t),
+                };
+                self.poses[i] = Some(pose);
+            }
+            report.filled.push(gap);
+        }
+        report
+    }
+
+    /// Fills gaps from a tool rigidly attached to the same segment, assuming the relative
+    /// transform between the two changes smoothly across the gap. Both series must be sampled on
+    /// the same frames.
+    pub fn fill_from<G: IsFrameOfReference>(&mut self, reference: &PoseSeries<G>, max_gap: usize) -> Result<FillReport> {
+        if self.len() != reference.len() {
+            return Err(Error::LengthMismatch { expected: self.len(), found: reference.len() });
+        }
+        let mut report = FillReport::default();
+        for gap in self.gaps() {
+            let relative = |i: usize| -> Option<(usize, Transform<G, F>)> {
+                Some((i, reference.poses.get(i)?.as_ref()?.mldivide(self.poses.get(i)?.as_ref()?).ok()?))
+            };
+            let before = gap.start.checked_sub(1).and_then(relative);
+            let after = relative(gap.end());
+            let covered = reference.poses[gap.start..gap.end()].iter().all(Option::is_some);
+            if gap.len > max_gap || !covered || (before.is_none() && after.is_none()) {
+                report.unfilled.push(gap);
+                continue;
+            }
+            for i in gap.start..gap.end() {
+                let rel = match (&before, &after) {
+                    (Some((a, ra)), Some((b, rb))) => ra.interpolate(rb, self.fraction(*a, *b, i)),
+                    (Some((_, r)), None) | (None, Some((_, r))) => *r,
+                    (None, None) => unreachable!(),
+                };
+                self.poses[i] = reference.poses[i].map(|p| p * rel);
+            }
+            report.filled.push(gap);
+        }
+        Ok(report)
+    }
+
+    fn pose(&self, i: usize) -> gT<F> {
+        self.poses[i].expect("gap bounds are valid samples")
+    }
+
+    fn fraction(&self, a: usize, b: usize, i: usize) -> f32 {
+        ((self.time[i] - self.time[a]) / (self.time[b] - self.time[a])) as f32
+    }
+
+    fn hermite(&self, a: usize, b: usize, t: f32) -> gT<F> {
+        let (pa, pb) = (self.pose(a), self.pose(b));
+        let h = (self.time[b] - self.time[a]) as f32;
+        // Use the outer neighbours for the tangents when they exist, otherwise the chord
+        let outer = |i: Option<usize>| i.and_then(|i| Some((i, self.poses.get(i)?.as_ref()?)));
+        let before = outer(a.checked_sub(1)).unwrap_or((a, &pa));
+        let after = outer(Some(b + 1)).unwrap_or((b, &pb));
+        let dt = |i: usize, j: usize| (self.time[j] - self.time[i]) as f32;
+
+        let position = |p: &gT<F>| p.translation().coords;
+        let m0 = (position(&pb) - position(before.1)) / dt(before.0, b) * h;
+        let m1 = (position(after.1) - position(&pa)) / dt(a, after.0) * h;
+        let translation = hermite(&position(&pa), &position(&pb), &m0, &m1, t);
+
+        let qa = pa.rotation().into_inner().coords;
+        let quaternion = |p: &gT<F>| {
+            let q = p.rotation().into_inner().coords;
+            if q.dot(&qa) < 0.0 { -q } else { q }
+        };
+        let m0 = (quaternion(&pb) - quaternion(before.1)) / dt(before.0, b) * h;
+        let m1 = (quaternion(after.1) - qa) / dt(a, after.0) * h;
+        let q = hermite(&qa, &quaternion(&pb), &m0, &m1, t);
+        let rotation = na::UnitQuaternion::new_normalize(na::Quaternion::from(q));
+
+        Transform::from_parts(&translation, &rotation)
+    }
+}
+
+fn hermite<const D: usize>(
+    p0: &na::SVector<f32, D>,
+    p1: &na::SVector<f32, D>,
+    m0: &na::SVector<f32, D>,
+    m1: &na::SVector<f32, D>,
+    t: f32,
+) -> na::SVector<f32, D> {
+    let (t2, t3) = (t * t, t * t * t);
+    p0 * (2.0 * t3 - 3.0 * t2 + 1.0) + m0 * (t3 - 2.0 * t2 + t) + p1 * (-2.0 * t3 + 3.0 * t2) + m1 * (t3 - t2)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bone_to_tracker::Femur;
+    use crate::Tracker;
+
+    fn moving(n: usize) -> PoseSeries<Tracker<Femur>> {
+        let time = (0..n).map(|i| i as f64 / 60.0).collect();
+        let poses = (0..n)
+            .map(|i| {
+                let t = i as f32 / 60.0;
+                let rotation = na::UnitQuaternion::from_axis_angle(&na::Vector3::z_axis(), t);
+                let translation = na::Vector3::new(10.0 * t, -5.0 * t, 2.0);
+                Some(Transform::from_parts(&translation, &rotation))
+            })
+            .collect();
+        PoseSeries::new(time, poses).unwrap()
+    }
+
+    fn assert_same<A: IsFrameOfReference, B: IsFrameOfReference>(a: &Transform<A, B>, b: &Transform<A, B>) {
+        assert_relative_eq!(a.inner(), b.inner(), epsilon = 1e-3);
+    }
+
+    #[test]
+    fn slerp_fills_constant_rate_motion() {
+        let truth = moving(20);
+        let mut series = truth.clone();
+        series.poses[5..9].fill(None);
+        let report = series.fill_gaps(Interpolation::Slerp, 5);
+        assert_eq!(report.filled, vec![Gap { start: 5, len: 4 }]);
+        for i in 5..9 {
+            assert_same(&series.pose(i), &truth.pose(i));
+        }
+    }
+
+    #[test]
+    fn spline_fills_and_respects_limits() {
+        let truth = moving(30);
+        let mut series = truth.clone();
+        series.poses[0..2].fill(None);
+        series.poses[10..13].fill(None);
+        series.poses[20..27].fill(None);
+        let report = series.fill_gaps(Interpolation::Spline, 5);
+        assert_eq!(report.filled, vec![Gap { start: 10, len: 3 }]);
+        assert_eq!(report.unfilled, vec![Gap { start: 0, len: 2 }, Gap { start: 20, len: 7 }]);
+        for i in 10..13 {
+            assert_same(&series.pose(i), &truth.pose(i));
+        }
+        assert!(series.poses[20].is_none());
+    }
+
+    #[test]
+    fn rigid_body_fill_uses_co_moving_tool() {
+        let reference = moving(20);
+        let offset = Transform::<Tracker<Femur>, Femur>::from_parts(
+            &na::Vector3::new(30.0, 0.0, -10.0),
+            &na::UnitQuaternion::from_axis_angle(&na::Vector3::x_axis(), 0.3),
+        );
+        let poses = reference.poses.iter().map(|p| p.map(|p| p * offset)).collect();
+        let truth = PoseSeries::<Femur>::new(reference.time.clone(), poses).unwrap();
+        let mut series = truth.clone();
+        series.poses[0..3].fill(None);
+        series.poses[10..15].fill(None);
+
+        let report = series.fill_from(&reference, 10).unwrap();
+        assert_eq!(report.filled.len(), 2);
+        for i in (0..3).chain(10..15) {
+            assert_same(&series.pose(i), &truth.pose(i));
+        }
+    }
+    #[test]
+    fn rejects_mismatched_series() {
+        let time = vec![0.0, 1.0];
+        assert!(matches!(PoseSeries::<Femur>::new(time, vec![None]), Err(Error::LengthMismatch { expected: 2, found: 1 })));
+        let mut series = moving(5);
+        let report = series.fill_from(&moving(6), 10);
+        assert!(matches!(report, Err(Error::LengthMismatch { expected: 5, found: 6 })));
+    }
+}
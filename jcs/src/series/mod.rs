@@ -0,0 +1,127 @@
+mod derivative;
+mod filter;
+mod gap_fill;
+
+pub use derivative::{differentiate, Differentiation};
+pub use filter::{residual_analysis, Butterworth};
+pub use gap_fill::{FillReport, Gap, Interpolation};
+
+use std::ops::Range;
+
+use input::Frame;
+
+use crate::data::{Datum, QualityThresholds};
+use crate::transform::{gT, IsFrameOfReference};
+use crate::{Error, Marker, Result};
+
+/// Timestamped poses of a frame of reference in global; `None` where the sample is missing.
+#[derive(Debug)]
+pub struct PoseSeries<F: IsFrameOfReference> {
+    time: Vec<f64>,
+    poses: Vec<Option<gT<F>>>,
+}
+
+impl<F: IsFrameOfReference> Clone for PoseSeries<F> {
+    fn clone(&self) -> Self {
+        Self {
+            time: self.time.clone(),
+            poses: self.poses.clone(),
+        }
+    }
+}
+
+impl<F: IsFrameOfReference> PoseSeries<F> {
+    /// Fails unless every pose has a timestamp.
+    pub fn new(time: Vec<f64>, poses: Vec<Option<gT<F>>>) -> Result<Self> {
+        if time.len() != poses.len() {
+            return Err(Error::LengthMismatch { expected: time.len(), found: poses.len() });
+        }
+        Ok(Self { time, poses })
+    }
+    pub fn time(&self) -> &[f64] {
+        &self.time
+    }
+    pub fn poses(&self) -> &[Option<gT<F>>] {
+        &self.poses
+    }
+    /// Same timebase with every present pose mapped, e.g. from tracker to bone.
+    pub(crate) fn map<G: IsFrameOfReference>(&self, f: impl Fn(&gT<F>) -> gT<G>) -> PoseSeries<G> {
+        PoseSeries {
+            time: self.time.clone(),
+            poses: self.poses.iter().map(|p| p.as_ref().map(&f)).collect(),
+        }
+    }
+    /// Mean sampling rate in Hz.
+    pub fn sample_rate(&self) -> f64 {
+        match (self.time.first(), self.time.last()) {
+            (Some(first), Some(last)) if last > first => (self.time.len() - 1) as f64 / (last - first),
+            _ => f64::NAN,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.poses.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.poses.is_empty()
+    }
+    /// Runs of consecutive missing samples.
+    pub fn gaps(&self) -> Vec<Gap> {
+        let mut gaps = Vec::new();
+        let mut start = None;
+        for (i, pose) in self.poses.iter().enumerate() {
+            match (pose, start) {
+                (None, None) => start = Some(i),
+                (Some(_), Some(s)) => {
+                    gaps.push(Gap { start: s, len: i - s });
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(s) = start {
+            gaps.push(Gap { start: s, len: self.poses.len() - s });
+        }
+        gaps
+    }
+}
+
+impl<F: IsFrameOfReference + Marker> PoseSeries<F> {
+    /// Poses of the tool matching `label`, dropping samples that fail the quality thresholds.
+    /// Fails on a frame without a timestamp.
+    pub fn from_frames<'a, I>(frames: I, label: &str, thresholds: &QualityThresholds) -> Result<Self>
+    where
+        I: IntoIterator<Item = &'a Frame>,
+    {
+        let (time, poses) = frames
+            .into_iter()
+            .enumerate()
+            .map(|(i, frame)| {
+                let pose = Datum::<F>::from_frame(frame, label)
+                    .filter(|d| d.is_valid(thresholds))
+                    .map(|d| d.to_transform());
+                Ok((frame.time().ok_or(Error::MissingTime(i))?, pose))
+            })
+            .collect::<Result<(Vec<_>, Vec<_>)>>()?;
+        Ok(Self { time, poses })
+    }
+}
+
+/// Index ranges of consecutive present values.
+fn runs<T>(values: &[Option<T>]) -> Vec<Range<usize>> {
+    let mut runs = Vec::new();
+    let mut start = None;
+    for (i, value) in values.iter().enumerate() {
+        match (value, start) {
+            (Some(_), None) => start = Some(i),
+            (None, Some(s)) => {
+                runs.push(s..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        runs.push(s..values.len());
+    }
+    runs
+}
@@ -0,0 +1,313 @@
+//! Session files tying a subject's digitisation to their trial recordings.
+
+use std::path::{Path, PathBuf};
+
+use input::Frame;
+use serde::{Deserialize, Serialize};
+
+use crate::bone_to_tracker::{Femur, Kinematics, Motion, Side, Tibia};
+use crate::data::{Datum, ProbeData, QualityThresholds};
+use crate::series::Butterworth;
+use crate::solvers::GroodAndSuntay;
+use crate::stream::KinematicsStream;
+use crate::{Error, Result, RigidBody};
+
+/// Contents of a session TOML file.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Session {
+    pub subject: String,
+    pub side: Side,
+    pub tools: Tools,
+    pub landmarks: Landmarks,
+    /// Cutoff in Hz of the zero-lag low-pass filter applied by [`Session::kinematics`]; unfiltered
+    /// when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<f64>,
+    #[serde(default, rename = "trial")]
+    pub trials: Vec<Trial>,
+    /// Directory relative trial paths are resolved against.
+    #[serde(skip)]
+    root: PathBuf,
+}
+
+/// Serial numbers of the bones' tools, as reported by the tracker, e.g. `3B21FC02`. Unlike tool
+/// names they stay the same when a tool definition is renamed or reloaded.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Tools {
+    pub femur: String,
+    pub tibia: String,
+}
+
+impl Tools {
+    pub fn new(femur: &str, tibia: &str) -> Result<Self> {
+        let tools = Self {
+            femur: femur.to_string(),
+            tibia: tibia.to_string(),
+        };
+        tools.check()?;
+        Ok(tools)
+    }
+    // NDI serial numbers are eight hexadecimal digits
+    fn check(&self) -> Result<()> {
+        for serial in [&self.femur, &self.tibia] {
+            if serial.len() != 8 || !serial.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(Error::Format(format!("{serial:?} is not a tool serial number")));
+            }
+        }
+        if self.femur == self.tibia {
+            return Err(Error::Format(format!("femur and tibia both tracked by {}", self.femur)));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Landmarks {
+    pub femur: BoneLandmarks,
+    pub tibia: BoneLandmarks,
+}
+
+/// Probe samples of a bone's digitisation and its tracker at the time, in global.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct BoneLandmarks {
+    pub medial: ProbeData,
+    pub lateral: ProbeData,
+    pub proximal_distal: ProbeData,
+    pub tracker: ProbeData,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Trial {
+    pub name: String,
+    pub path: PathBuf,
+    /// Static trial defining the neutral pose.
+    #[serde(default)]
+    pub reference: bool,
+}
+
+impl BoneLandmarks {
+    /// Samples a digitised bone was built from. The tracker only keeps its pose, so it is
+    /// saved under the medial sample's probe name.
+    pub fn from_body<const ID: usize>(body: &RigidBody<ID>) -> Self {
+        let medial = body.medial.probe_data();
+        Self {
+            medial: medial.clone(),
+            lateral: body.lateral.probe_data().clone(),
+            proximal_distal: body.proximal_distal.probe_data().clone(),
+            tracker: medial.with_pose(body.tracker.translation().coords, body.tracker.rotation()),
+        }
+    }
+    fn to_body<const ID: usize>(&self, side: Side) -> Result<RigidBody<ID>> {
+        RigidBody::new(
+            side,
+            self.medial.clone(),
+            self.lateral.clone(),
+            self.proximal_distal.clone(),
+            self.tracker.clone(),
+        )
+    }
+}
+
+impl Session {
+    pub fn new(subject: &str, tools: Tools, femur: &Femur, tibia: &Tibia) -> Self {
+        Self {
+            subject: subject.to_string(),
+            side: femur.side,
+            tools,
+            landmarks: Landmarks {
+                femur: BoneLandmarks::from_body(femur),
+                tibia: BoneLandmarks::from_body(tibia),
+            },
+            filter: None,
+            trials: Vec::new(),
+            root: PathBuf::new(),
+        }
+    }
+    /// Fails on malformed TOML and on tools not given by serial number.
+    pub fn from_toml(s: &str) -> Result<Self> {
+        let session: Self = toml::from_str(s).map_err(|e| Error::Format(e.to_string()))?;
+        session.tools.check()?;
+        Ok(session)
+    }
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string(self).map_err(|e| Error::Format(e.to_string()))
+    }
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut session = Self::from_toml(&std::fs::read_to_string(path)?)?;
+        session.root = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(session)
+    }
+    /// Writes the session; trial paths are saved as given.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        Ok(std::fs::write(path, self.to_toml()?)?)
+    }
+    pub fn add_trial(&mut self, name: &str, path: impl Into<PathBuf>, reference: bool) {
+        self.trials.push(Trial {
+            name: name.to_string(),
+            path: path.into(),
+            reference,
+        });
+    }
+    pub fn trial(&self, name: &str) -> Option<&Trial> {
+        self.trials.iter().find(|t| t.name == name)
+    }
+    pub fn reference(&self) -> Option<&Trial> {
+        self.trials.iter().find(|t| t.reference)
+    }
+    /// Femur with its anatomical frame defined relative to its tracker.
+    pub fn femur(&self) -> Result<Femur> {
+        self.landmarks.femur.to_body(self.side)
+    }
+    pub fn tibia(&self) -> Result<Tibia> {
+        self.landmarks.tibia.to_body(self.side)
+    }
+    fn path(&self, trial: &Trial) -> Result<String> {
+        let path = self.root.join(&trial.path);
+        path.to_str()
+            .map(str::to_string)
+            .ok_or_else(|| Error::Format(format!("{} is not valid UTF-8", path.display())))
+    }
+    pub fn frames(&self, trial: &Trial) -> Result<Vec<Frame>> {
+        Ok(input::polaris::read(&self.path(trial)?)?)
+    }
+    /// Tibiofemoral kinematics of a trial computed while reading, for recordings too long to load.
+    /// Never filtered: the zero-lag filter needs the whole trial.
+    pub fn stream(
+        &self,
+        trial: &Trial,
+        thresholds: &QualityThresholds,
+    ) -> Result<impl Iterator<Item = Result<Option<Motion>>>> {
+        self.stream_frames(input::polaris::frames(&self.path(trial)?)?, thresholds)
+    }
+    /// Tibiofemoral kinematics of frames from any source, such as a live
+    /// [`Connection`](input::combined::Connection).
+    pub fn stream_frames<I: Iterator<Item = input::Result<Frame>>>(
+        &self,
+        frames: I,
+        thresholds: &QualityThresholds,
+    ) -> Result<impl Iterator<Item = Result<Option<Motion>>> + use<I>> {
+        Ok(KinematicsStream::new(
+            GroodAndSuntay::tibiofemoral(),
+            &self.femur()?,
+            &self.tibia()?,
+            (&self.tools.femur, &self.tools.tibia),
+            thresholds,
+            frames,
+        ))
+    }
+    /// Tibiofemoral kinematics of a trial, filtered if the session sets a cutoff. Fails if either
+    /// tool never appears in the recording.
+    pub fn kinematics(&self, trial: &Trial, thresholds: &QualityThresholds) -> Result<Kinematics> {
+        let frames = self.frames(trial)?;
+        for serial in [&self.tools.femur, &self.tools.tibia] {
+            if !frames.iter().flat_map(|f| &f.tools).any(|t| t.port.serial == *serial) {
+                return Err(Error::MissingTool(serial.clone()));
+            }
+        }
+        let data = frames
+            .iter()
+            .map(|f| (Datum::from_frame(f, &self.tools.femur), Datum::from_frame(f, &self.tools.tibia)));
+        let kinematics = Kinematics::from_data(&GroodAndSuntay::tibiofemoral(), &self.femur()?, &self.tibia()?, data, thresholds);
+        match self.filter {
+            Some(cutoff) => Ok(kinematics.filtered(&Butterworth::new(cutoff, sample_rate(&frames)?)?)),
+            None => Ok(kinematics),
+        }
+    }
+}
+
+/// Mean sampling rate of a recording in Hz.
+fn sample_rate(frames: &[Frame]) -> Result<f64> {
+    let time = |i: usize| frames.get(i).and_then(Frame::time).ok_or(Error::MissingTime(i));
+    let last = frames.len().saturating_sub(1);
+    Ok(last as f64 / (time(last)? - time(0)?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data::ProbeRawData;
+
+    const NAME: &str = "Black Probe";
+    const LABEL: &str = "Probe";
+
+    fn knee() -> (Femur, Tibia) {
+        let femur = Femur::new(
+            Side::Left,
+            ProbeRawData::new(NAME, LABEL, 0.8228, 0.1357, 0.4408, -0.3318, 15.3196, -54.9971, -2097.6023).into(),
+            ProbeRawData::new(NAME, LABEL, 0.4031, 0.4746, 0.4195, -0.6603, 16.9156, 16.2064, -2059.3142).into(),
+            ProbeRawData::new(NAME, LABEL, 0.4280, 0.4662, 0.4471, -0.6319, -8.5689, 15.8874, -2131.4353).into(),
+            ProbeRawData::new(NAME, LABEL, 0.9573733, -0.0372205, -0.1895465, 0.2147628, -149.371, -19.411, -2148.287).into(),
+        ).unwrap();
+        let tibia = Tibia::new(
+            Side::Left,
+            ProbeRawData::new(NAME, LABEL, 0.8156, 0.0787, 0.4628, -0.3381, 66.899, -61.4777, -2078.4102).into(),
+            ProbeRawData::new(NAME, LABEL, 0.4197, 0.4198, 0.3991, -0.6987, 65.8513, -6.3346, -2031.8842).into(),
+            ProbeRawData::new(NAME, LABEL, 0.4268, 0.2327, 0.5690, -0.6632, 209.2022, -37.8499, -2040.4506).into(),
+            ProbeRawData::new(NAME, LABEL, 0.0230, -0.1878, 0.0213, 0.9817, 128.0411, 196.8627, -2024.9063).into(),
+        ).unwrap();
+        (femur, tibia)
+    }
+
+    #[test]
+    fn keeps_the_anatomical_frames() {
+        let (femur, tibia) = knee();
+        let session = Session::new("S01", Tools::new("38220010", "3B21FC02").unwrap(), &femur, &tibia);
+        assert_relative_eq!(session.femur().unwrap().in_tracker().inner(), femur.in_tracker().inner(), epsilon = 1e-3);
+        assert_relative_eq!(session.tibia().unwrap().in_tracker().inner(), tibia.in_tracker().inner(), epsilon = 1e-3);
+    }
+
+    #[test]
+    fn saves_and_processes_trials() {
+        let (femur, tibia) = knee();
+        let mut session = Session::new("S01", Tools::new("38220010", "3B21FC02").unwrap(), &femur, &tibia);
+        session.add_trial("standing", "data.csv", true);
+        session.add_trial("flexion", "data.csv", false);
+
+        let dir = std::env::temp_dir().join(format!("jcs-session-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::copy(concat!(env!("CARGO_MANIFEST_DIR"), "/../input/data.csv"), dir.join("data.csv")).unwrap();
+        session.save(dir.join("session.toml")).unwrap();
+        let loaded = Session::load(dir.join("session.toml")).unwrap();
+        // Quaternions are normalised again on loading, so compare to within rounding
+        let (saved, digitised) = (&loaded.landmarks.femur.medial, femur.medial.probe_data());
+        assert_eq!(saved.translation(), digitised.translation());
+        assert_relative_eq!(saved.rotation(), digitised.rotation(), epsilon = 1e-6);
+        assert_relative_eq!(loaded.tibia().unwrap().in_tracker().inner(), tibia.in_tracker().inner(), epsilon = 1e-4);
+        assert_eq!(loaded.trials, session.trials);
+        assert_eq!(loaded.side, Side::Left);
+        assert_eq!(loaded.reference().unwrap().name, "standing");
+
+        let kinematics = loaded.kinematics(loaded.trial("flexion").unwrap(), &QualityThresholds::default()).unwrap();
+        let streamed: Vec<_> = loaded.stream(loaded.trial("flexion").unwrap(), &QualityThresholds::default()).unwrap().map(Result::unwrap).collect();
+        assert_eq!(streamed, kinematics.frames());
+        let mut unknown = loaded.clone();
+        unknown.tools.tibia = "3B21FC03".to_string();
+        let error = unknown.kinematics(unknown.trial("flexion").unwrap(), &QualityThresholds::default());
+        assert!(matches!(error, Err(Error::MissingTool(serial)) if serial == "3B21FC03"));
+        // Tool names and labels are not accepted in place of serial numbers
+        unknown.tools.tibia = "T".to_string();
+        assert!(matches!(Session::from_toml(&unknown.to_toml().unwrap()), Err(Error::Format(_))));
+        assert!(Tools::new("38220010", "38220010").is_err());
+
+        let mut filtered = loaded.clone();
+        filtered.filter = Some(6.0);
+        let smooth = filtered.kinematics(filtered.trial("flexion").unwrap(), &QualityThresholds::default()).unwrap();
+        assert_eq!(smooth.gaps(), kinematics.gaps());
+        assert_ne!(smooth.frames(), kinematics.frames());
+        filtered.filter = Some(1000.0);
+        let error = filtered.kinematics(filtered.trial("flexion").unwrap(), &QualityThresholds::default());
+        assert!(matches!(error, Err(Error::InvalidCutoff { .. })));
+        assert_eq!(Session::from_toml(&filtered.to_toml().unwrap()).unwrap().filter, Some(1000.0));
+        std::fs::remove_dir_all(&dir).unwrap();
+        let frames = input::polaris::read(concat!(env!("CARGO_MANIFEST_DIR"), "/../input/data.csv")).unwrap();
+        let data = frames.iter().map(|f| (Datum::from_frame(f, "Y"), Datum::from_frame(f, "T")));
+        let expected = Kinematics::from_data(&GroodAndSuntay::tibiofemoral(), &femur, &tibia, data, &QualityThresholds::default());
+        assert_eq!(kinematics.len(), expected.len());
+        for (a, b) in kinematics.frames().iter().zip(expected.frames()) {
+            for (a, b) in a.unwrap().dofs().into_iter().zip(b.unwrap().dofs()) {
+                assert_relative_eq!(a, b, epsilon = 1e-2);
+            }
+        }
+    }
+}
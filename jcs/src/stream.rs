@@ -0,0 +1,131 @@
+//! Frame-by-frame kinematics for recordings too long to hold in memory.
+
+use std::io::Write;
+
+use input::Frame;
+
+use crate::bone_to_tracker::{DefinedTracker, Motion, Side};
+use crate::data::{Datum, QualityThresholds};
+use crate::solvers::Solver;
+use crate::transform::tT;
+use crate::{Result, RigidBody};
+
+/// Motion of every frame as it is read, `None` where either tracker was missing or failed the
+/// quality thresholds. Yields the same motions as [`Kinematics::from_data`](crate::Kinematics::from_data)
+/// while holding a single frame at a time.
+pub struct KinematicsStream<S, I, const A: usize, const B: usize> {
+    solver: S,
+    first: tT<RigidBody<A>>,
+    second: tT<RigidBody<B>>,
+    side: Side,
+    labels: (String, String),
+    thresholds: QualityThresholds,
+    frames: I,
+}
+
+impl<S, I, const A: usize, const B: usize> KinematicsStream<S, I, A, B>
+where
+    S: Solver<F = RigidBody<A>, T = RigidBody<B>>,
+    RigidBody<A>: DefinedTracker,
+    RigidBody<B>: DefinedTracker,
+    I: Iterator<Item = input::Result<Frame>>,
+{
+    /// `labels` pick the tools of the two bones out of each frame, as in [`Datum::from_frame`].
+    /// `frames` can be read from an export with [`input::polaris::frames`] or polled live from
+    /// [`input::combined::Connection::frames`].
+    pub fn new(
+        solver: S,
+        first: &RigidBody<A>,
+        second: &RigidBody<B>,
+        labels: (&str, &str),
+        thresholds: &QualityThresholds,
+        frames: I,
+    ) -> Self {
+        Self {
+            solver,
+            first: first.in_tracker(),
+            second: second.in_tracker(),
+            side: first.side,
+            labels: (labels.0.to_string(), labels.1.to_string()),
+            thresholds: *thresholds,
+            frames,
+        }
+    }
+
+    fn solve(&self, frame: &Frame) -> Option<Motion> {
+        let a: Datum<_> = Datum::from_frame(frame, &self.labels.0)?;
+        let b: Datum<_> = Datum::from_frame(frame, &self.labels.1)?;
+        if !(a.is_valid(&self.thresholds) && b.is_valid(&self.thresholds)) {
+            return None;
+        }
+        Some(self.solver.solve(a.to_transform() * self.first, b.to_transform() * self.second, self.side))
+    }
+}
+
+impl<S, I, const A: usize, const B: usize> Iterator for KinematicsStream<S, I, A, B>
+where
+    S: Solver<F = RigidBody<A>, T = RigidBody<B>>,
+    RigidBody<A>: DefinedTracker,
+    RigidBody<B>: DefinedTracker,
+    I: Iterator<Item = input::Result<Frame>>,
+{
+    type Item = Result<Option<Motion>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.frames.next()?;
+        Some(frame.map(|f| self.solve(&f)).map_err(Into::into))
+    }
+}
+
+/// Writes motions as they arrive, one CSV row per frame with empty fields for gaps, and returns
+/// the number of frames. Stops at the first error.
+pub fn write_csv<W: Write, I: Iterator<Item = Result<Option<Motion>>>>(writer: &mut W, motions: I) -> Result<usize> {
+    writeln!(writer, "frame,{}", Motion::DOF_NAMES.join(","))?;
+    let mut count = 0;
+    for (i, motion) in motions.enumerate() {
+        match motion? {
+            Some(m) => writeln!(writer, "{i},{}", m.dofs().map(|d| d.to_string()).join(","))?,
+            None => writeln!(writer, "{i}{}", ",".repeat(Motion::DOF_NAMES.len()))?,
+        }
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bone_to_tracker::{Femur, Kinematics, Tibia};
+    use crate::solvers::GroodAndSuntay;
+    use crate::synthetic::SyntheticKnee;
+
+    #[test]
+    fn matches_batch_processing() {
+        let knee = SyntheticKnee::new(Side::Left).with_dropout(0.1).with_seed(5);
+        let motions: Vec<_> = (0..30).map(|i| Motion::from_dofs([i as f32 * 3.0, 2.0, 1.0, 3.0, 1.0, -0.5])).collect();
+        let path = std::env::temp_dir().join(format!("stream-{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        knee.write(path, &motions).unwrap();
+        let (femur, tibia): (Femur, Tibia) = knee.rigid_bodies(&Motion::from_dofs([0.0; 6])).unwrap();
+        let thresholds = QualityThresholds::default();
+
+        let frames = input::polaris::frames(path).unwrap();
+        let stream = KinematicsStream::new(GroodAndSuntay::tibiofemoral(), &femur, &tibia, ("Y", "T"), &thresholds, frames);
+        let mut out = Vec::new();
+        assert_eq!(write_csv(&mut out, stream).unwrap(), motions.len());
+
+        let frames = input::polaris::read(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let data = frames.iter().map(|f| (Datum::from_frame(f, "Y"), Datum::from_frame(f, "T")));
+        let batch = Kinematics::from_data(&GroodAndSuntay::tibiofemoral(), &femur, &tibia, data, &thresholds);
+        let out = String::from_utf8(out).unwrap();
+        assert!(batch.gaps() > 0);
+        for (line, motion) in out.lines().skip(1).zip(batch.frames()) {
+            let fields: Vec<f32> = line.split(',').skip(1).filter_map(|f| f.parse().ok()).collect();
+            match motion {
+                Some(m) => assert_eq!(fields, m.dofs()),
+                None => assert!(fields.is_empty()),
+            }
+        }
+    }
+}
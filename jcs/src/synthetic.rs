@@ -0,0 +1,272 @@
+//! Synthetic Polaris trials with known kinematics, for validating the solvers.
+
+use input::polaris::Port;
+use input::{Frame, State, ToolRecord};
+use nalgebra as na;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, StandardNormal};
+
+use crate::bone_to_tracker::{Femur, Motion, Side, Tibia};
+use crate::data::{ProbeData, ProbeRawData};
+use crate::solvers::GroodAndSuntay;
+use crate::transform::{gT, IsFrameOfReference, Transform};
+use crate::{Result, Tracker};
+
+/// Landmarks and tracker mounting of one bone, in its anatomical frame.
+#[derive(Debug)]
+pub struct BoneGeometry<RB: IsFrameOfReference> {
+    pub medial: na::Point3<f32>,
+    pub lateral: na::Point3<f32>,
+    pub proximal_distal: na::Point3<f32>,
+    pub tracker: Transform<RB, Tracker<RB>>,
+    pub port: Port,
+}
+
+impl<RB: IsFrameOfReference> BoneGeometry<RB> {
+    /// Epicondyles (or plateau edges) `width` apart on the i axis and the shaft landmark
+    /// `length` along k, which is negative for a distal landmark.
+    pub fn knee(side: Side, width: f32, length: f32, tracker: Transform<RB, Tracker<RB>>, port: Port) -> Self {
+        // i points to the subject's right on both sides
+        let lateral = na::Point3::new(side.sign() * width / 2.0, 0.0, 0.0);
+        Self {
+            medial: -lateral,
+            lateral,
+            proximal_distal: na::Point3::new(0.0, 0.0, length),
+            tracker,
+            port,
+        }
+    }
+    // Probe samples of the landmarks and the tracker with the bone at `pose`
+    fn digitise(&self, pose: &gT<RB>) -> [ProbeData; 4] {
+        let point = |p: &na::Point3<f32>| {
+            let p = pose.transform_point(p);
+            ProbeRawData::new("Synthetic Probe", "Probe", 1.0, 0.0, 0.0, 0.0, p.x, p.y, p.z).into()
+        };
+        let tracker = *pose * self.tracker;
+        let (q, t) = (tracker.rotation(), tracker.translation());
+        let tracker = ProbeRawData::new("Synthetic Probe", "Probe", q.w, q.i, q.j, q.k, t.x, t.y, t.z).into();
+        [point(&self.medial), point(&self.lateral), point(&self.proximal_distal), tracker]
+    }
+}
+
+// Tracker on a plate in front of the bone, tilted towards the camera
+fn mount<RB: IsFrameOfReference>(x: f32, z: f32) -> Transform<RB, Tracker<RB>> {
+    Transform::from_parts(&na::Vector3::new(x, 60.0, z), &na::UnitQuaternion::from_euler_angles(1.2, 0.3, -0.4))
+}
+
+/// Tracker noise as standard deviations.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Noise {
+    /// mm, per axis.
+    pub translation: f32,
+    /// Degrees, per axis.
+    pub rotation: f32,
+}
+
+/// Knee with a fixed femur and a tibia driven through prescribed `Motion`.
+#[derive(Debug)]
+pub struct SyntheticKnee {
+    pub side: Side,
+    pub femur: BoneGeometry<Femur>,
+    pub tibia: BoneGeometry<Tibia>,
+    /// Femoral anatomical frame in global throughout the trial.
+    pub femur_pose: gT<Femur>,
+    pub sample_rate: f64,
+    noise: Noise,
+    marker_error: f32,
+    dropout: f64,
+    seed: u64,
+}
+
+impl SyntheticKnee {
+    /// Adult-sized knee about 2 m in front of the camera, tracked by the Y and T junctions.
+    pub fn new(side: Side) -> Self {
+        let port = |handle: u8, name: &str, serial: &str| Port {
+            handle,
+            name: name.to_string(),
+            serial: serial.to_string(),
+        };
+        Self {
+            side,
+            femur: BoneGeometry::knee(side, 80.0, 200.0, mount(20.0, 150.0), port(1, "BrainLAB Y Junction", "38220010")),
+            tibia: BoneGeometry::knee(side, 75.0, -200.0, mount(-20.0, -150.0), port(2, "BrainLAB T Junction", "3B21FC02")),
+            femur_pose: Transform::from_parts(
+                &na::Vector3::new(0.0, 0.0, -2000.0),
+                &na::UnitQuaternion::from_euler_angles(-1.4, 0.1, 0.2),
+            ),
+            sample_rate: 20.0,
+            noise: Noise::default(),
+            marker_error: 0.15,
+            dropout: 0.0,
+            seed: 0,
+        }
+    }
+    pub fn with_noise(mut self, noise: Noise) -> Self {
+        self.noise = noise;
+        self
+    }
+    /// Typical RMS marker fit error reported with each pose, in mm, 0.15 by default. It is
+    /// reported as is and does not depend on the pose noise, as the camera fits the markers
+    /// before the pose is known.
+    pub fn with_marker_error(mut self, rms: f32) -> Self {
+        self.marker_error = rms;
+        self
+    }
+    /// Probability that a tool is reported missing in a frame.
+    pub fn with_dropout(mut self, probability: f64) -> Self {
+        self.dropout = probability;
+        self
+    }
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+    fn tibia_pose(&self, motion: &Motion) -> Result<gT<Tibia>> {
+        GroodAndSuntay::tibiofemoral().tibia_pose(self.femur_pose, motion, self.side)
+    }
+    /// Femur and tibia digitised without error with the knee at `motion`.
+    pub fn rigid_bodies(&self, motion: &Motion) -> Result<(Femur, Tibia)> {
+        let [medial, lateral, proximal, tracker] = self.femur.digitise(&self.femur_pose);
+        let femur = Femur::new(self.side, medial, lateral, proximal, tracker)?;
+        let [medial, lateral, distal, tracker] = self.tibia.digitise(&self.tibia_pose(motion)?);
+        Ok((femur, Tibia::new(self.side, medial, lateral, distal, tracker)?))
+    }
+    /// One frame per prescribed motion, with noise and dropouts applied to the trackers.
+    pub fn frames(&self, motions: &[Motion]) -> Result<Vec<Frame>> {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let (translation, rotation) = (self.noise.translation, self.noise.rotation.to_radians());
+        let mut sample = |port: &Port, pose: (na::Point3<f32>, na::UnitQuaternion<f32>), frame: usize| {
+            let time = frame as f64 / self.sample_rate;
+            if rng.random::<f64>() < self.dropout {
+                return ToolRecord {
+                    port: port.clone(),
+                    frame: frame as u64,
+                    time,
+                    state: State::Missing,
+                    rotation: None,
+                    translation: None,
+                    error: None,
+                    markers: 0,
+                };
+            }
+            let mut vector = |sigma: f32| {
+                na::Vector3::from_fn(|_, _| {
+                    let z: f32 = StandardNormal.sample(&mut rng);
+                    sigma * z
+                })
+            };
+            let q = na::UnitQuaternion::from_scaled_axis(vector(rotation)) * pose.1;
+            let t = pose.0 + vector(translation);
+            // Varies by about a fifth from frame to frame
+            let jitter: f32 = StandardNormal.sample(&mut rng);
+            let error = (self.marker_error * (1.0 + 0.2 * jitter)).abs();
+            ToolRecord {
+                port: port.clone(),
+                frame: frame as u64,
+                time,
+                state: State::Ok,
+                rotation: Some([q.w, q.i, q.j, q.k]),
+                translation: Some([t.x, t.y, t.z]),
+                error: Some(error),
+                markers: 3,
+            }
+        };
+        let femur_tracker = self.femur_pose * self.femur.tracker;
+        motions
+            .iter()
+            .enumerate()
+            .map(|(i, motion)| {
+                let tibia_tracker = self.tibia_pose(motion)? * self.tibia.tracker;
+                let tools = vec![
+                    sample(&self.femur.port, (femur_tracker.translation(), femur_tracker.rotation()), i),
+                    sample(&self.tibia.port, (tibia_tracker.translation(), tibia_tracker.rotation()), i),
+                ];
+                Ok(Frame { tools })
+            })
+            .collect()
+    }
+    /// Writes the trial in the same format as an NDI Track export.
+    pub fn write(&self, path: &str, motions: &[Motion]) -> Result<()> {
+        Ok(input::polaris::write(path, &self.frames(motions)?)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bone_to_tracker::Kinematics;
+    use crate::data::{Datum, QualityThresholds};
+
+    fn trial(n: usize) -> Vec<Motion> {
+        (0..n)
+            .map(|i| {
+                let t = i as f32 / n as f32 * std::f32::consts::TAU;
+                Motion::from_dofs([45.0 - 40.0 * t.cos(), 10.0 * t.sin(), 3.0 * t.sin(), 5.0 * t.sin(), 2.0, -1.0])
+            })
+            .collect()
+    }
+
+    fn kinematics(knee: &SyntheticKnee, frames: &[Frame]) -> Kinematics {
+        let (femur, tibia) = knee.rigid_bodies(&Motion::from_dofs([0.0; 6])).unwrap();
+        let data = frames.iter().map(|f| (Datum::from_frame(f, "Y"), Datum::from_frame(f, "T")));
+        Kinematics::from_data(&GroodAndSuntay::tibiofemoral(), &femur, &tibia, data, &QualityThresholds::default())
+    }
+
+    #[test]
+    fn recovers_prescribed_motion_through_csv() {
+        let motions = trial(40);
+        for side in [Side::Right, Side::Left] {
+            let knee = SyntheticKnee::new(side).with_dropout(0.1).with_seed(7);
+            let path = std::env::temp_dir().join(format!("synthetic-{side:?}-{}.csv", std::process::id()));
+            let path = path.to_str().unwrap();
+            knee.write(path, &motions).unwrap();
+            let frames = input::polaris::read(path).unwrap();
+            std::fs::remove_file(path).unwrap();
+
+            let kinematics = kinematics(&knee, &frames);
+            assert_eq!(kinematics.len(), motions.len());
+            assert!(kinematics.gaps() > 0);
+            for (solved, prescribed) in kinematics.frames().iter().zip(&motions) {
+                let Some(solved) = solved else { continue };
+                for (a, b) in solved.dofs().into_iter().zip(prescribed.dofs()) {
+                    assert_relative_eq!(a, b, epsilon = 2e-2);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn noise_perturbs_but_stays_close() {
+        let motions = trial(20);
+        let noise = Noise { translation: 0.2, rotation: 0.1 };
+        let knee = SyntheticKnee::new(Side::Right).with_noise(noise).with_seed(3);
+        let kinematics = kinematics(&knee, &knee.frames(&motions).unwrap());
+        assert_eq!(kinematics.gaps(), 0);
+        let errors: Vec<f32> = kinematics
+            .frames()
+            .iter()
+            .zip(&motions)
+            .map(|(solved, prescribed)| (solved.unwrap().flexion() - prescribed.flexion()).abs())
+            .collect();
+        assert!(errors.iter().any(|e| *e > 1e-3));
+        assert!(errors.iter().all(|e| *e < 2.0));
+        // Same seed, same trial
+        assert_eq!(knee.frames(&motions).unwrap(), knee.frames(&motions).unwrap());
+    }
+
+    #[test]
+    fn marker_error_does_not_follow_pose_noise() {
+        let motions = trial(20);
+        let noise = Noise { translation: 2.0, rotation: 0.5 };
+        let knee = SyntheticKnee::new(Side::Right).with_noise(noise).with_seed(5);
+        let frames = knee.frames(&motions).unwrap();
+        assert_eq!(kinematics(&knee, &frames).gaps(), 0);
+        let errors: Vec<f32> = frames.iter().flat_map(|f| &f.tools).map(|t| t.error.unwrap()).collect();
+        assert!(errors.iter().all(|e| *e < 0.5));
+        assert!(errors.windows(2).any(|w| w[0] != w[1]));
+
+        let knee = knee.with_marker_error(1.5);
+        assert!(kinematics(&knee, &knee.frames(&motions).unwrap()).gaps() > 0);
+    }
+}
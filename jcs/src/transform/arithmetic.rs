@@ -1,7 +1,12 @@
 use nalgebra::{self as na, UnitQuaternion, VectorView4};
 use std::{marker::PhantomData, ops};
 
-pub trait IsFrameOfReference {}
+use crate::{Error, Result};
+
+pub trait IsFrameOfReference {
+    /// Name used to tag serialized data, e.g. `Tracker<RigidBody<1>>`.
+    fn name() -> String;
+}
 
 #[derive(Debug)]
 pub struct Transform<T, V>
@@ -14,6 +19,22 @@ where
     _to: PhantomData<V>,
 }
 
+impl<T, V> Clone for Transform<T, V>
+where
+    T: IsFrameOfReference,
+    V: IsFrameOfReference,
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T, V> Copy for Transform<T, V>
+where
+    T: IsFrameOfReference,
+    V: IsFrameOfReference,
+{
+}
+
 impl<T, V> std::fmt::Display for Transform<T, V>
 where
     T: IsFrameOfReference,
@@ -23,6 +44,7 @@ where
         write!(f, "{}", self.data.to_homogeneous())
     }
 }
+/// `self \ rhs`, i.e. `self.inverse() * rhs` without forming the inverse.
 pub trait Mldivide<Rhs> {
     type Output;
     fn mldivide(&self, rhs: &Rhs) -> Self::Output;
@@ -52,17 +74,12 @@ where
     F: IsFrameOfReference,
     T: IsFrameOfReference,
 {
-    type Output = Transform<F, T>;
+    type Output = Result<Transform<F, T>>;
 
     fn mldivide(&self, rhs: &Transform<G, T>) -> Self::Output {
         let lu = na::LU::new(self.data.into());
-        let matrix = lu.solve(&rhs.data.into()).unwrap();
-        let data = na::Transform3::from_matrix_unchecked(matrix);
-        Self::Output {
-            data,
-            _from: PhantomData,
-            _to: PhantomData,
-        }
+        let matrix = lu.solve(&rhs.data.into()).ok_or(Error::SingularTransform)?;
+        Ok(Transform::new(na::Transform3::from_matrix_unchecked(matrix)))
     }
 }
 
@@ -78,11 +95,36 @@ where
             _to: PhantomData,
         }
     }
+    pub fn from_parts(translation: &na::Vector3<f32>, rotation: &na::UnitQuaternion<f32>) -> Transform<T, V> {
+        let matrix = na::Matrix4::new_translation(translation) * rotation.to_homogeneous();
+        Self::new(na::Transform3::from_matrix_unchecked(matrix))
+    }
+    /// Linear interpolation of the translation and spherical interpolation of the rotation.
+    pub fn interpolate(&self, other: &Self, t: f32) -> Transform<T, V> {
+        let translation = self.translation().coords.lerp(&other.translation().coords, t);
+        let rotation = self.rotation().slerp(&other.rotation(), t);
+        Self::from_parts(&translation, &rotation)
+    }
+    pub fn inverse(&self) -> Result<Transform<V, T>> {
+        self.data.try_inverse().map(Transform::new).ok_or(Error::SingularTransform)
+    }
+    /// Inverse of a rotation followed by a translation, which always exists. Only valid for
+    /// transforms known to be rigid, such as tracker poses built from a unit quaternion.
+    pub fn rigid_inverse(&self) -> Transform<V, T> {
+        let rotation = self.inner().matrix().fixed_view::<3, 3>(0, 0).transpose();
+        let translation = -(rotation * self.origin().coords);
+        let matrix = na::Matrix4::new_translation(&translation) * rotation.to_homogeneous();
+        Transform::new(na::Transform3::from_matrix_unchecked(matrix))
+    }
+    /// Maps a point given in `V` into `T`.
+    pub fn transform_point(&self, point: &na::Point3<f32>) -> na::Point3<f32> {
+        self.data.transform_point(point)
+    }
     pub fn inner(&self) -> &na::Transform3<f32> {
         &self.data
     }
     pub fn translation(&self) -> na::Point3<f32> {
-        na::Point3::from_homogeneous(self.inner().to_homogeneous().column(3).into()).unwrap()
+        self.origin()
     }
     pub fn rotation(&self) -> na::UnitQuaternion<f32> {
         let rotmat = self.inner().matrix().fixed_view::<3, 3>(0, 0).into_owned();
@@ -117,4 +159,14 @@ mod test {
 
         assert_relative_eq!((t*rotation).to_homogeneous(), unit_q.to_homogeneous(), epsilon=1e-2)
     }
+
+    #[test]
+    fn singular_transforms_are_errors() {
+        let pose = Transform::<Global, Femur>::from_parts(&na::Vector3::new(1.0, 2.0, 3.0), &UnitQuaternion::from_euler_angles(0.1, 0.2, 0.3));
+        let flat = Transform::<Global, Femur>::new(na::Transform3::from_matrix_unchecked(na::Matrix4::from_diagonal(&na::Vector4::new(1.0, 1.0, 0.0, 1.0))));
+        assert!(matches!(flat.inverse(), Err(Error::SingularTransform)));
+        assert!(matches!(flat.mldivide(&pose), Err(Error::SingularTransform)));
+        assert_relative_eq!(pose.inverse().unwrap().inner(), pose.rigid_inverse().inner(), epsilon = 1e-5);
+        assert_relative_eq!(pose.mldivide(&pose).unwrap().inner(), &na::Transform3::identity(), epsilon = 1e-5);
+    }
 }
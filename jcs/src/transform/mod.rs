@@ -1,6 +1,8 @@
 #![allow(non_camel_case_types)]
 mod arithmetic;
+mod serialize;
 pub use arithmetic::{Mldivide, IsFrameOfReference, Transform};
+pub(crate) use serialize::check_frame;
 
 use crate::{bone_to_tracker::Global, Tracker};
 pub type gT<X> = Transform<Global, X>;
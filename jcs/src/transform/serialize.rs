@@ -0,0 +1,70 @@
+use nalgebra as na;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{IsFrameOfReference, Transform};
+use crate::Error as FrameError;
+
+// Frames are stored by name so that data saved for one pair of frames cannot be loaded as another
+#[derive(Serialize, Deserialize)]
+struct Tagged {
+    from: String,
+    to: String,
+    /// Homogeneous matrix, row by row.
+    matrix: [[f32; 4]; 4],
+}
+
+/// Fails unless `found` is the name of frame `F`.
+pub(crate) fn check_frame<F: IsFrameOfReference, E: Error>(found: &str) -> Result<(), E> {
+    let expected = F::name();
+    if found == expected {
+        Ok(())
+    } else {
+        Err(E::custom(FrameError::InvalidFrame {
+            expected,
+            found: found.to_string(),
+        }))
+    }
+}
+
+impl<T: IsFrameOfReference, V: IsFrameOfReference> Serialize for Transform<T, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let m = self.inner().matrix();
+        Tagged {
+            from: T::name(),
+            to: V::name(),
+            matrix: std::array::from_fn(|r| std::array::from_fn(|c| m[(r, c)])),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T: IsFrameOfReference, V: IsFrameOfReference> Deserialize<'de> for Transform<T, V> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let tagged = Tagged::deserialize(deserializer)?;
+        check_frame::<T, _>(&tagged.from)?;
+        check_frame::<V, _>(&tagged.to)?;
+        let matrix = na::Matrix4::from_fn(|r, c| tagged.matrix[r][c]);
+        Ok(Transform::new(na::Transform3::from_matrix_unchecked(matrix)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bone_to_tracker::{Femur, Global, Tibia};
+    use crate::transform::tT;
+
+    #[test]
+    fn round_trips_with_frame_tags() {
+        let transform = tT::<Tibia>::from_parts(&na::Vector3::new(1.5, -2.0, 30.25), &na::UnitQuaternion::from_euler_angles(0.1, 0.2, 0.3));
+        let text = toml::to_string(&transform).unwrap();
+        assert!(text.contains("from = \"Tracker<RigidBody<1>>\""));
+        let loaded: tT<Tibia> = toml::from_str(&text).unwrap();
+        assert_eq!(loaded.inner(), transform.inner());
+
+        let error = toml::from_str::<tT<Femur>>(&text).unwrap_err().to_string();
+        assert!(error.contains("expected Tracker<RigidBody<2>>, found Tracker<RigidBody<1>>"), "{error}");
+        assert!(toml::from_str::<Transform<Global, Tibia>>(&text).is_err());
+    }
+}
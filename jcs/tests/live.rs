@@ -0,0 +1,38 @@
+//! Polls a synthetic recording from the replay simulator over TCP and checks that the live
+//! kinematics match those computed from the file.
+#![cfg(feature = "knee")]
+
+use input::combined::Connection;
+use input::polaris;
+use input::simulator::Simulator;
+use jcs::prelude::*;
+use jcs::stream::KinematicsStream;
+use jcs::synthetic::SyntheticKnee;
+
+#[test]
+fn live_kinematics_match_the_recording() {
+    let knee = SyntheticKnee::new(Side::Right).with_dropout(0.1).with_seed(11);
+    let motions: Vec<_> = (0..40).map(|i| Motion::from_dofs([i as f32 * 2.0, 3.0, -2.0, 1.0, 2.0, -1.0])).collect();
+    let path = std::env::temp_dir().join(format!("live-{}.csv", std::process::id()));
+    let path = path.to_str().unwrap();
+    knee.write(path, &motions).unwrap();
+    let (femur, tibia): (Femur, Tibia) = knee.rigid_bodies(&Motion::from_dofs([0.0; 6])).unwrap();
+    let thresholds = QualityThresholds::default();
+
+    let (address, _) = Simulator::new(path).unwrap().spawn("127.0.0.1:0").unwrap();
+    let mut connection = Connection::connect(address).unwrap().with_ports(polaris::ports(path).unwrap());
+    connection.start().unwrap();
+    let frames = connection.frames().take(motions.len());
+    let live: Vec<_> = KinematicsStream::new(GroodAndSuntay::tibiofemoral(), &femur, &tibia, ("Y", "T"), &thresholds, frames)
+        .collect::<jcs::Result<_>>()
+        .unwrap();
+
+    let frames = polaris::frames(path).unwrap();
+    let recorded: Vec<_> = KinematicsStream::new(GroodAndSuntay::tibiofemoral(), &femur, &tibia, ("Y", "T"), &thresholds, frames)
+        .collect::<jcs::Result<_>>()
+        .unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(live.len(), motions.len());
+    assert!(recorded.iter().any(Option::is_none));
+    assert_eq!(live, recorded);
+}
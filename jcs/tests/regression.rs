@@ -0,0 +1,144 @@
+//! Runs every case in `tests/fixtures` through the full chain (CSV, quality gating, anatomical
+//! frames, Grood & Suntay) and compares the kinematics against the checked-in expected file.
+//!
+//! The expected files are regression snapshots: the kinematics this crate produced when the case
+//! was added, so they catch changes in results but not errors the pipeline already had. Each
+//! `case.toml` records how its snapshot was generated; a snapshot is replaced only by hand after
+//! reviewing the change. Trials are paths relative to the case directory, so recordings already
+//! in the repository are not copied.
+#![cfg(feature = "knee")]
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use jcs::data::{ProbeData, ProbeRawData};
+use jcs::prelude::*;
+use serde::Deserialize;
+
+// Per-DOF tolerance of cases that do not set their own: degrees for the rotations, mm for the
+// translations
+const TOLERANCE: [f32; 6] = [1e-2, 1e-2, 1e-2, 1e-2, 1e-2, 1e-2];
+
+#[derive(Deserialize)]
+struct Case {
+    side: String,
+    trial: PathBuf,
+    expected: PathBuf,
+    /// In the order of `Motion::DOF_NAMES`.
+    #[serde(default)]
+    tolerance: Option<[f32; 6]>,
+    femur: Bone,
+    tibia: Bone,
+}
+
+#[derive(Deserialize)]
+struct Bone {
+    tool: String,
+    medial: [f32; 7],
+    lateral: [f32; 7],
+    proximal_distal: [f32; 7],
+    tracker: [f32; 7],
+}
+
+fn probe([q0, qx, qy, qz, x, y, z]: [f32; 7]) -> ProbeData {
+    ProbeRawData::new("Black Probe", "Probe", q0, qx, qy, qz, x, y, z).into()
+}
+
+fn side(name: &str) -> Side {
+    match name {
+        "left" => Side::Left,
+        "right" => Side::Right,
+        other => panic!("unknown side {other}"),
+    }
+}
+
+fn run(dir: &Path, case: &Case) -> Kinematics {
+    let side = side(&case.side);
+    let femur = Femur::new(side, probe(case.femur.medial), probe(case.femur.lateral), probe(case.femur.proximal_distal), probe(case.femur.tracker)).unwrap();
+    let tibia = Tibia::new(side, probe(case.tibia.medial), probe(case.tibia.lateral), probe(case.tibia.proximal_distal), probe(case.tibia.tracker)).unwrap();
+    let frames = input::polaris::read(dir.join(&case.trial).to_str().unwrap()).unwrap();
+    let data = frames
+        .iter()
+        .map(|f| (Datum::from_frame(f, &case.femur.tool), Datum::from_frame(f, &case.tibia.tool)));
+    Kinematics::from_data(&GroodAndSuntay::tibiofemoral(), &femur, &tibia, data, &QualityThresholds::default())
+}
+
+fn read_expected(path: &Path) -> Vec<Option<[f32; 6]>> {
+    let text = std::fs::read_to_string(path).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+    text.lines()
+        .skip(1)
+        .map(|line| {
+            let fields: Vec<_> = line.split(',').skip(1).collect();
+            if fields.iter().all(|f| f.is_empty()) {
+                return None;
+            }
+            let mut dofs = [0.0; 6];
+            for (d, f) in dofs.iter_mut().zip(fields) {
+                *d = f.parse().unwrap_or_else(|_| panic!("{}: bad value {f:?}", path.display()));
+            }
+            Some(dofs)
+        })
+        .collect()
+}
+
+/// Differences beyond tolerance, one report line per DOF with the worst frame.
+fn compare(kinematics: &Kinematics, expected: &[Option<[f32; 6]>], tolerance: &[f32; 6]) -> Vec<String> {
+    let mut failures = Vec::new();
+    if kinematics.len() != expected.len() {
+        failures.push(format!("{} frames, expected {}", kinematics.len(), expected.len()));
+    }
+    let mut worst = [(0.0f32, 0usize); 6];
+    for (i, (actual, expected)) in kinematics.frames().iter().zip(expected).enumerate() {
+        match (actual, expected) {
+            (Some(actual), Some(expected)) => {
+                for (dof, (a, e)) in actual.dofs().iter().zip(expected).enumerate() {
+                    // NaN always counts as a failure
+                    let error = if a.is_nan() != e.is_nan() { f32::INFINITY } else { (a - e).abs() };
+                    if error > worst[dof].0 {
+                        worst[dof] = (error, i);
+                    }
+                }
+            }
+            (None, None) => {}
+            (actual, _) => failures.push(format!("frame {i}: gap mismatch, got {actual:?}")),
+        }
+    }
+    for (dof, (error, frame)) in worst.iter().enumerate() {
+        if *error > tolerance[dof] {
+            failures.push(format!("{}: max error {error} at frame {frame} (tolerance {})", Motion::DOF_NAMES[dof], tolerance[dof]));
+        }
+    }
+    failures
+}
+
+#[test]
+fn pipeline_matches_fixtures() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let mut report = String::new();
+    let mut cases = 0;
+    for entry in std::fs::read_dir(&root).unwrap() {
+        let dir = entry.unwrap().path();
+        let Ok(text) = std::fs::read_to_string(dir.join("case.toml")) else { continue };
+        let case: Case = toml::from_str(&text).unwrap_or_else(|e| panic!("{}: {e}", dir.display()));
+        let kinematics = run(&dir, &case);
+        cases += 1;
+        let tolerance = case.tolerance.unwrap_or(TOLERANCE);
+        for failure in compare(&kinematics, &read_expected(&dir.join(&case.expected)), &tolerance) {
+            writeln!(report, "{}: {failure}", dir.file_name().unwrap().to_string_lossy()).unwrap();
+        }
+    }
+    assert!(cases > 0, "no fixtures found in {}", root.display());
+    assert!(report.is_empty(), "kinematics changed:\n{report}");
+}
+
+#[test]
+fn reports_changed_dofs() {
+    let kinematics: Kinematics = [Some(Motion::from_dofs([10.0, 0.0, 0.0, 0.0, 0.0, 0.0])), None].into_iter().collect();
+    assert!(compare(&kinematics, &[Some([10.0, 0.0, 0.0, 0.0, 0.0, 0.0]), None], &TOLERANCE).is_empty());
+    let failures = compare(&kinematics, &[Some([10.5, 0.0, 0.0, 0.0, 0.0, 0.0]), Some([0.0; 6])], &TOLERANCE);
+    assert_eq!(failures.len(), 2);
+    assert!(failures[1].starts_with("flexion: max error 0.5 at frame 0"));
+    // A case's own tolerance replaces the default
+    let loose = [1.0, 1e-2, 1e-2, 1e-2, 1e-2, 1e-2];
+    assert!(compare(&kinematics, &[Some([10.5, 0.0, 0.0, 0.0, 0.0, 0.0]), None], &loose).is_empty());
+}
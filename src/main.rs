@@ -1,3 +1,222 @@
-fn main() {
-    println!("Hello, world!");
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use input::combined::Connection;
+use input::simulator::Simulator;
+use input::tool::ToolDefinition;
+use jcs::batch::Batch;
+use jcs::broadcast::{Broadcaster, Encoding};
+use jcs::data::QualityThresholds;
+use jcs::session::Session;
+
+const USAGE: &str = "usage: opticaltracking batch <session.toml> <output dir> [--recordings <dir>] [--threads <n>] [--filter <Hz>]
+       opticaltracking replay <recording.csv> [--address <host:port>] [--speed <x>] [--loop]
+       opticaltracking live <session.toml> <tracker host:port> [--send <host:port>] [--osc] [--ports <export.csv>] [--roms <dir>]
+
+batch writes the tibiofemoral kinematics of every trial in the session, or of every CSV in
+--recordings, to <output dir>/<trial>.csv with a summary.csv of failures, low-pass filtered at
+--filter Hz if given or set in the session.
+
+replay serves a recording as a tracker speaking the Combined API over TCP, on 127.0.0.1:8765
+unless --address is given, following its timestamps when --speed is given.
+
+live tracks the session's femur and tibia from a tracker speaking the Combined API and sends
+each motion over UDP to 127.0.0.1:9000 unless --send is given, as JSON or with --osc as OSC.
+--ports names the tools after those of an export from the same setup. --roms uploads the
+definitions of the session's passive tools from <dir>/<serial number>.rom.";
+
+const REPLAY_ADDRESS: &str = "127.0.0.1:8765";
+const LIVE_TARGET: &str = "127.0.0.1:9000";
+
+struct BatchArgs {
+    session: PathBuf,
+    output: PathBuf,
+    recordings: Option<PathBuf>,
+    threads: Option<usize>,
+    filter: Option<f64>,
+}
+
+fn parse(args: &[String]) -> Result<BatchArgs, String> {
+    let mut positional = Vec::new();
+    let mut recordings = None;
+    let mut threads = None;
+    let mut filter = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--recordings" => recordings = Some(args.next().ok_or("--recordings needs a directory")?.into()),
+            "--threads" => {
+                let n = args.next().ok_or("--threads needs a number")?;
+                threads = Some(n.parse().map_err(|_| format!("invalid thread count `{n}`"))?);
+            }
+            "--filter" => {
+                let hz = args.next().ok_or("--filter needs a cutoff in Hz")?;
+                filter = Some(hz.parse().map_err(|_| format!("invalid cutoff `{hz}`"))?);
+            }
+            _ => positional.push(arg),
+        }
+    }
+    match positional[..] {
+        [session, output] => Ok(BatchArgs {
+            session: session.into(),
+            output: output.into(),
+            recordings,
+            threads,
+            filter,
+        }),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+struct ReplayArgs {
+    recording: String,
+    address: String,
+    speed: Option<f64>,
+    looping: bool,
+}
+
+fn parse_replay(args: &[String]) -> Result<ReplayArgs, String> {
+    let mut positional = Vec::new();
+    let mut address = REPLAY_ADDRESS.to_string();
+    let mut speed = None;
+    let mut looping = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--address" => address = args.next().ok_or("--address needs host:port")?.clone(),
+            "--speed" => {
+                let x = args.next().ok_or("--speed needs a number")?;
+                speed = Some(x.parse().ok().filter(|x: &f64| *x > 0.0).ok_or(format!("invalid speed `{x}`"))?);
+            }
+            "--loop" => looping = true,
+            _ => positional.push(arg),
+        }
+    }
+    match positional[..] {
+        [recording] => Ok(ReplayArgs {
+            recording: recording.clone(),
+            address,
+            speed,
+            looping,
+        }),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+struct LiveArgs {
+    session: PathBuf,
+    tracker: String,
+    target: String,
+    encoding: Encoding,
+    ports: Option<String>,
+    roms: Option<PathBuf>,
+}
+
+fn parse_live(args: &[String]) -> Result<LiveArgs, String> {
+    let mut positional = Vec::new();
+    let mut target = LIVE_TARGET.to_string();
+    let mut encoding = Encoding::Json;
+    let mut ports = None;
+    let mut roms = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--send" => target = args.next().ok_or("--send needs host:port")?.clone(),
+            "--osc" => encoding = Encoding::Osc,
+            "--ports" => ports = Some(args.next().ok_or("--ports needs an export")?.clone()),
+            "--roms" => roms = Some(args.next().ok_or("--roms needs a directory")?.into()),
+            _ => positional.push(arg),
+        }
+    }
+    match positional[..] {
+        [session, tracker] => Ok(LiveArgs {
+            session: session.into(),
+            tracker: tracker.clone(),
+            target,
+            encoding,
+            ports,
+            roms,
+        }),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn batch(args: BatchArgs) -> jcs::Result<bool> {
+    let mut session = Session::load(&args.session)?;
+    if let Some(dir) = &args.recordings {
+        session.trials.clear();
+        session.add_directory(dir)?;
+    }
+    if args.filter.is_some() {
+        session.filter = args.filter;
+    }
+    let mut batch = Batch::new(&session);
+    if let Some(threads) = args.threads {
+        batch = batch.with_threads(threads);
+    }
+    let report = batch.run(&args.output)?;
+    print!("{report}");
+    Ok(report.failed.is_empty())
+}
+
+fn replay(args: ReplayArgs) -> jcs::Result<bool> {
+    let mut simulator = Simulator::new(&args.recording)?;
+    if let Some(speed) = args.speed {
+        simulator = simulator.with_speed(speed);
+    }
+    if args.looping {
+        simulator = simulator.looping();
+    }
+    let listener = std::net::TcpListener::bind(&args.address)?;
+    println!("replaying {} on {}", args.recording, listener.local_addr()?);
+    simulator.listen(listener)?;
+    Ok(true)
+}
+
+fn live(args: LiveArgs) -> jcs::Result<bool> {
+    let session = Session::load(&args.session)?;
+    let broadcaster = Broadcaster::new(&args.target)?.with_encoding(args.encoding);
+    let mut connection = Connection::connect(&args.tracker)?;
+    if let Some(export) = &args.ports {
+        connection = connection.with_ports(input::polaris::ports(export)?);
+    }
+    if let Some(dir) = &args.roms {
+        let tools = [&session.tools.femur, &session.tools.tibia]
+            .into_iter()
+            .map(|serial| ToolDefinition::load(dir.join(format!("{serial}.rom"))))
+            .collect::<input::Result<_>>()?;
+        connection = connection.with_tools(tools);
+    }
+    connection.start()?;
+    println!("sending motions from {} to {}", args.tracker, args.target);
+    // The tracker hanging up ends the session
+    let frames = connection
+        .frames()
+        .take_while(|f| !matches!(f, Err(input::Error::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof));
+    let motions = session.stream_frames(frames, &QualityThresholds::default())?;
+    let frames = broadcaster.broadcast(motions)?;
+    println!("{frames} frames");
+    Ok(true)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.split_first() {
+        Some((command, rest)) if command == "batch" => parse(rest).map(batch),
+        Some((command, rest)) if command == "replay" => parse_replay(rest).map(replay),
+        Some((command, rest)) if command == "live" => parse_live(rest).map(live),
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(Ok(true)) => ExitCode::SUCCESS,
+        Ok(Ok(false)) => ExitCode::FAILURE,
+        Ok(Err(e)) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+        Err(message) => {
+            eprintln!("{message}");
+            ExitCode::from(2)
+        }
+    }
 }